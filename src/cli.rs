@@ -0,0 +1,272 @@
+use warehouse::trajectory::FORMATS;
+
+pub const USAGE: &'static str = "Usage: warehouse [options]
+
+Options:
+    -s, --scenario NAME    built-in scenario to simulate (default: one_bot)
+    -l, --layout PATH      load the warehouse layout from a JSON file (serde feature)
+        --replay PATH      resume from a recorded trajectory in any output format
+        --at TICK          resume from the frame recorded at TICK (default: the last frame)
+        --resume PATH      resume from a checkpoint written by --checkpoint (serde feature)
+    -t, --ticks N          number of ticks to simulate (default: 4)
+    -u, --until-idle       run until the scheduler is idle, at most --ticks ticks
+                           (default: 10000)
+    -o, --output PATH      write the trajectory to PATH, or to stdout with - (default: data/data.json)
+    -f, --format FORMAT    trajectory format: json, jsonl or binary (default: json)
+    -k, --keyframes N      write delta frames: every object every N ticks and only changed objects
+                           in between (default: every object on every tick)
+        --seed N           generate object IDs from a random seed (default: sequential IDs)
+        --checkpoint PATH  write the full simulation state to PATH after the run (serde feature)
+        --events PATH      write command and object events as JSON lines to PATH, or to stdout with -
+    -r, --report PATH      write a JSON report of run statistics to PATH, or to stdout with -
+        --summary          print a table of run statistics to stderr
+    -h, --help             print this help";
+
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    Scenario(String),
+    #[cfg(feature = "serde")]
+    Layout(String),
+    Replay(String),
+    #[cfg(feature = "serde")]
+    Checkpoint(String)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    pub source: Source,
+    pub ticks: u64,
+    pub until_idle: bool,
+    pub output: String,
+    pub format: String,
+    pub keyframes: Option<u64>,
+    pub seed: Option<u64>,
+    pub at: Option<u64>,
+    #[cfg(feature = "serde")]
+    pub checkpoint: Option<String>,
+    pub events: Option<String>,
+    pub report: Option<String>,
+    pub summary: bool,
+    pub help: bool
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self {
+            source: Source::Scenario("one_bot".to_owned()),
+            ticks: 4,
+            until_idle: false,
+            output: "data/data.json".to_owned(),
+            format: "json".to_owned(),
+            keyframes: None,
+            seed: None,
+            at: None,
+            #[cfg(feature = "serde")]
+            checkpoint: None,
+            events: None,
+            report: None,
+            summary: false,
+            help: false
+        }
+    }
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Self::new();
+        let mut ticks = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("missing value for {}", name));
+            match &arg[..] {
+                "-s" | "--scenario" => options.source = Source::Scenario(value(&arg)?),
+                #[cfg(feature = "serde")]
+                "-l" | "--layout" => options.source = Source::Layout(value(&arg)?),
+                "--replay" => options.source = Source::Replay(value(&arg)?),
+                #[cfg(feature = "serde")]
+                "--resume" => options.source = Source::Checkpoint(value(&arg)?),
+                #[cfg(feature = "serde")]
+                "--checkpoint" => options.checkpoint = Some(value(&arg)?),
+                #[cfg(not(feature = "serde"))]
+                "-l" | "--layout" | "--resume" | "--checkpoint" => return Err(format!("{} requires the serde feature", arg)),
+                "--events" => options.events = Some(value(&arg)?),
+                "--at" => options.at = Some(parse_number(&arg, &value(&arg)?)?),
+                "-t" | "--ticks" => ticks = Some(parse_number(&arg, &value(&arg)?)?),
+                "-u" | "--until-idle" => options.until_idle = true,
+                "-o" | "--output" => options.output = value(&arg)?,
+                "-f" | "--format" => {
+                    let format = value(&arg)?;
+                    if !FORMATS.contains(&&format[..]) {
+                        return Err(format!("unknown format {}", format));
+                    }
+                    options.format = format;
+                },
+                "-k" | "--keyframes" => match parse_number(&arg, &value(&arg)?)? {
+                    0 => return Err(format!("{} must be at least 1", arg)),
+                    keyframes => options.keyframes = Some(keyframes)
+                },
+                "--seed" => options.seed = Some(parse_number(&arg, &value(&arg)?)?),
+                "-r" | "--report" => options.report = Some(value(&arg)?),
+                "--summary" => options.summary = true,
+                "-h" | "--help" => options.help = true,
+                _ => return Err(format!("unknown option {}", arg))
+            }
+        }
+        options.ticks = match (ticks, options.until_idle) {
+            (Some(ticks), _) => ticks,
+            (None, true) => 10000,
+            (None, false) => options.ticks
+        };
+        let stdout = [Some(&options.output), options.events.as_ref(), options.report.as_ref()].iter()
+            .filter(|path| path.map(|path| &path[..]) == Some("-"))
+            .count();
+        if stdout > 1 {
+            return Err("only one of --output, --events and --report can write to stdout".to_owned());
+        }
+        match options.source {
+            Source::Replay(_) => {},
+            _ => if options.at.is_some() {
+                return Err("--at requires --replay".to_owned());
+            }
+        }
+        #[cfg(feature = "serde")]
+        {
+            if let Source::Checkpoint(_) = options.source {
+                if options.seed.is_some() {
+                    return Err("--seed cannot be used with --resume".to_owned());
+                }
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number(name: &str, value: &str) -> Result<u64, String> {
+    value.parse().map_err(|_| format!("invalid number {} for {}", value, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_default() {
+        assert_eq!(parse(&[]).unwrap(), Options::new());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse() {
+        let options = parse(&["-l", "layouts/one_bot.json", "--ticks", "20", "-o", "-"]).unwrap();
+        assert_eq!(options.source, Source::Layout("layouts/one_bot.json".to_owned()));
+        assert_eq!(options.ticks, 20);
+        assert_eq!(options.output, "-");
+        assert_eq!(parse(&["-s", "one_bot"]).unwrap().source, Source::Scenario("one_bot".to_owned()));
+    }
+
+    #[test]
+    fn test_seed() {
+        assert_eq!(parse(&["--seed", "42"]).unwrap().seed, Some(42));
+        assert_eq!(parse(&[]).unwrap().seed, None);
+    }
+
+    #[test]
+    fn test_report() {
+        let options = parse(&["--report", "report.json", "--summary"]).unwrap();
+        assert_eq!(options.report, Some("report.json".to_owned()));
+        assert!(options.summary);
+        assert_eq!(parse(&["-r", "-"]).unwrap().report, Some("-".to_owned()));
+    }
+
+    #[test]
+    fn test_keyframes() {
+        assert_eq!(parse(&[]).unwrap().keyframes, None);
+        assert_eq!(parse(&["-k", "1"]).unwrap().keyframes, Some(1));
+        assert_eq!(parse(&["--keyframes", "10"]).unwrap().keyframes, Some(10));
+    }
+
+    #[test]
+    fn test_replay() {
+        let options = parse(&["--at", "12", "--replay", "data/data.json"]).unwrap();
+        assert_eq!(options.source, Source::Replay("data/data.json".to_owned()));
+        assert_eq!(options.at, Some(12));
+        assert_eq!(parse(&["--replay", "data/data.json"]).unwrap().at, None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_checkpoint() {
+        let options = parse(&["--resume", "a.json", "--checkpoint", "b.json"]).unwrap();
+        assert_eq!(options.source, Source::Checkpoint("a.json".to_owned()));
+        assert_eq!(options.checkpoint, Some("b.json".to_owned()));
+    }
+
+    #[test]
+    fn test_events() {
+        assert_eq!(parse(&["--events", "-"]).unwrap().events, Some("-".to_owned()));
+        assert_eq!(parse(&[]).unwrap().events, None);
+    }
+
+    #[test]
+    fn test_until_idle() {
+        let options = parse(&["--until-idle"]).unwrap();
+        assert!(options.until_idle);
+        assert_eq!(options.ticks, 10000);
+        assert_eq!(parse(&["-u", "-t", "50"]).unwrap().ticks, 50);
+        assert!(!parse(&["-t", "50"]).unwrap().until_idle);
+    }
+
+    #[test]
+    #[should_panic(expected="missing value for --ticks")]
+    fn test_missing_value() {
+        parse(&["--ticks"]).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected="invalid number")]
+    fn test_invalid_number() {
+        parse(&["--ticks", "many"]).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected="--keyframes must be at least 1")]
+    fn test_zero_keyframes() {
+        parse(&["--keyframes", "0"]).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected="unknown format")]
+    fn test_unknown_format() {
+        parse(&["--format", "xml"]).unwrap();
+    }
+
+    #[cfg(not(feature = "serde"))]
+    #[test]
+    fn test_serde_options() {
+        assert_eq!(parse(&["-l", "layouts/one_bot.json"]).unwrap_err(), "-l requires the serde feature");
+        assert_eq!(parse(&["--resume", "a.json"]).unwrap_err(), "--resume requires the serde feature");
+        assert_eq!(parse(&["--checkpoint", "b.json"]).unwrap_err(), "--checkpoint requires the serde feature");
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let stdout = "only one of --output, --events and --report can write to stdout";
+        assert_eq!(parse(&["--events", "-", "-o", "-"]).unwrap_err(), stdout);
+        assert_eq!(parse(&["--events", "-", "--report", "-"]).unwrap_err(), stdout);
+        assert!(parse(&["--events", "-", "-o", "data/data.json"]).is_ok());
+        assert_eq!(parse(&["--at", "12"]).unwrap_err(), "--at requires --replay");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_resume_seed() {
+        assert_eq!(parse(&["--resume", "a.json", "--seed", "42"]).unwrap_err(), "--seed cannot be used with --resume");
+    }
+
+    #[test]
+    #[should_panic(expected="unknown option")]
+    fn test_unknown_option() {
+        parse(&["--fast"]).unwrap();
+    }
+}
@@ -1,19 +1,153 @@
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_json;
+
 mod warehouse;
 mod worlddata;
+mod cli;
 
-use worlddata::{ Runner, OneRunner };
+use worlddata::{ Runner, OneRunner, scenario, SCENARIOS };
+use worlddata::util::WorldData;
+use warehouse::{ IdGenerator, OrderBook, EventWriter };
+#[cfg(feature = "serde")]
+use warehouse::Snapshot;
+use warehouse::trajectory;
+use cli::{ Options, Source, USAGE };
 
-use std::fs::File;
+use std::env;
+use std::fs::{ self, File };
+use std::io::{ self, BufWriter };
 use std::io::prelude::*;
+use std::path::Path;
+use std::process;
+
+#[cfg(feature = "serde")]
+fn resume(path: &str) -> io::Result<OneRunner> {
+    Ok(OneRunner::with_snapshot(Snapshot::load(io::BufReader::new(File::open(path)?))?))
+}
+
+#[cfg(feature = "serde")]
+fn checkpoint(runner: &OneRunner, path: &str) -> io::Result<()> {
+    let snapshot = runner.snapshot().map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+    snapshot.save(output(path)?)
+}
+
+fn load(options: &Options, ids: IdGenerator) -> io::Result<(WorldData, OrderBook)> {
+    match options.source {
+        Source::Scenario(ref name) => scenario(name, ids).map(|data| (data, OrderBook::new())).ok_or(io::Error::new(io::ErrorKind::NotFound,
+            format!("unknown scenario {}, available: {}", name, SCENARIOS.join(", ")))),
+        #[cfg(feature = "serde")]
+        Source::Layout(ref path) => worlddata::load_layout(path, ids),
+        Source::Replay(ref path) => {
+            // commands in flight are not recorded, so nothing would ever release their locks
+            let (data, orders) = worlddata::load_replay(path, options.at, ids)?;
+            worlddata::unlock_all(&data);
+            Ok((data, orders))
+        },
+        #[cfg(feature = "serde")]
+        Source::Checkpoint(_) => Err(io::Error::new(io::ErrorKind::InvalidInput, "a checkpoint restores a whole runner"))
+    }
+}
 
-fn main() -> std::io::Result<()> {
-    let mut runner = OneRunner::new();
-    let mut file = File::create("data/data.json")?;
-    runner.tick_start(&mut file)?;
-    runner.tick_and_save(&mut file)?;
-    runner.tick_and_save(&mut file)?;
-    runner.tick_and_save(&mut file)?;
-    runner.tick_and_save(&mut file)?;
-    runner.tick_end(&mut file)?;
+fn output(path: &str) -> io::Result<Box<Write>> {
+    if path == "-" {
+        return Ok(Box::new(io::stdout()));
+    }
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(Box::new(BufWriter::new(File::create(path)?)))
+}
+
+fn run(options: &Options) -> io::Result<()> {
+    let ids = match options.seed {
+        Some(seed) => IdGenerator::seeded(seed),
+        None => IdGenerator::sequential()
+    };
+    let mut runner = match options.source {
+        #[cfg(feature = "serde")]
+        Source::Checkpoint(ref path) => resume(path)?,
+        _ => {
+            let (data, orders) = load(options, ids)?;
+            OneRunner::with_orders(data, orders)
+        }
+    };
+    let sink = trajectory::sink(&options.format, output(&options.output)?)
+        .ok_or(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown format {}", options.format)))?;
+    let mut sink = match options.keyframes {
+        Some(keyframes) => Box::new(trajectory::DeltaSink::new(sink, keyframes)),
+        None => sink
+    };
+    let events = match options.events {
+        Some(ref path) => Some(EventWriter::new(output(path)?)),
+        None => None
+    };
+    if let Some(ref events) = events {
+        runner.world().lock().unwrap().subscribe(events.clone());
+    }
+    for _ in 0..options.ticks {
+        runner.tick_and_save(&mut *sink)?;
+        if options.until_idle && runner.idle() {
+            break;
+        }
+    }
+    runner.finish(&mut *sink)?;
+    if let Some(ref events) = events {
+        events.lock().unwrap().finish()?;
+    }
+    #[cfg(feature = "serde")]
+    {
+        if let Some(ref path) = options.checkpoint {
+            checkpoint(&runner, path)?;
+        }
+    }
+    report(&runner, options)
+}
+
+fn report(runner: &OneRunner, options: &Options) -> io::Result<()> {
+    let world = runner.world();
+    let world = world.lock().unwrap();
+    let orders = runner.orders();
+    let orders = orders.lock().unwrap();
+    if let Some(ref path) = options.report {
+        let mut file = output(path)?;
+        let failures = runner.failures().iter()
+            .map(|failure| failure.render())
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(file, "{{ \"metrics\": {}, \"orders\": {{ \"total\": {}, \"completed\": {}, \"late\": {}, \"throughput\": {} }}, \"failures\": [{}] }}\n",
+            world.metrics().render(), orders.orders().len(), orders.completed(), orders.late(), orders.throughput(), failures)?;
+        file.flush()?;
+    }
+    if options.summary {
+        eprint!("{}", world.metrics().table());
+        eprintln!("{} of {} orders completed, {} late", orders.completed(), orders.orders().len(), orders.late());
+        if runner.failures().len() > 0 {
+            eprintln!("{} commands failed", runner.failures().len());
+        }
+    }
     Ok(())
 }
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+    if let Err(err) = run(&options) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,164 @@
+use warehouse::{ Item, quote };
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Temperature {
+    Ambient,
+    Chilled,
+    Frozen
+}
+
+impl Default for Temperature {
+    fn default() -> Self {
+        Temperature::Ambient
+    }
+}
+
+impl Temperature {
+    pub fn render(&self) -> &'static str {
+        match *self {
+            Temperature::Ambient => "ambient",
+            Temperature::Chilled => "chilled",
+            Temperature::Frozen => "frozen"
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ItemInfo {
+    pub id: Item,
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub weight: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub volume: u32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub category: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub temperature: Temperature,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hazard: Option<String>
+}
+
+impl ItemInfo {
+    pub fn new(id: Item, name: &str) -> Self {
+        Self {
+            id: id,
+            name: name.to_owned(),
+            ..Self::default()
+        }
+    }
+    pub fn render(&self) -> String {
+        let hazard = match self.hazard {
+            Some(ref hazard) => quote(hazard),
+            None => "null".to_owned()
+        };
+        format!("{{ \"id\": {}, \"name\": {}, \"weight\": {}, \"volume\": {}, \"category\": {}, \"temperature\": \"{}\", \"hazard\": {} }}",
+            self.id, quote(&self.name), self.weight, self.volume, quote(&self.category), self.temperature.render(), hazard)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ItemCatalog {
+    items: BTreeMap<Item, ItemInfo>
+}
+
+impl ItemCatalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn insert(&mut self, info: ItemInfo) {
+        self.items.insert(info.id, info);
+    }
+    pub fn get(&self, item: Item) -> Option<&ItemInfo> {
+        self.items.get(&item)
+    }
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn name(&self, item: Item) -> Option<&str> {
+        self.get(item).map(|info| &info.name[..])
+    }
+    pub fn weight(&self, item: Item) -> u32 {
+        self.get(item).map_or(0, |info| info.weight)
+    }
+    pub fn volume(&self, item: Item) -> u32 {
+        self.get(item).map_or(0, |info| info.volume)
+    }
+    pub fn in_category(&self, category: &str) -> Vec<Item> {
+        self.items.values()
+            .filter(|info| info.category == category)
+            .map(|info| info.id)
+            .collect()
+    }
+    pub fn render(&self) -> String {
+        let items = self.items.values()
+            .map(|info: &ItemInfo| info.render())
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("[{}]", items)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ItemCatalog {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.collect_seq(self.items.values())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ItemCatalog {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let mut catalog = ItemCatalog::new();
+        for info in Vec::<ItemInfo>::deserialize(deserializer)? {
+            catalog.insert(info);
+        }
+        Ok(catalog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> ItemCatalog {
+        let mut catalog = ItemCatalog::new();
+        catalog.insert(ItemInfo { weight: 3, volume: 2, category: "dairy".to_owned(), temperature: Temperature::Chilled, ..ItemInfo::new(2, "milk") });
+        catalog.insert(ItemInfo { weight: 1, category: "cleaning".to_owned(), hazard: Some("corrosive".to_owned()), ..ItemInfo::new(1, "bleach") });
+        catalog
+    }
+
+    #[test]
+    fn test_lookup() {
+        let catalog = catalog();
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog.name(2), Some("milk"));
+        assert_eq!(catalog.weight(2), 3);
+        assert_eq!(catalog.volume(1), 0);
+        assert_eq!(catalog.weight(3), 0);
+        assert_eq!(catalog.in_category("dairy"), vec![2]);
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(catalog().render(), "[{ \"id\": 1, \"name\": \"bleach\", \"weight\": 1, \"volume\": 0, \"category\": \"cleaning\", \"temperature\": \"ambient\", \"hazard\": \"corrosive\" }, \
+            { \"id\": 2, \"name\": \"milk\", \"weight\": 3, \"volume\": 2, \"category\": \"dairy\", \"temperature\": \"chilled\", \"hazard\": null }]");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let catalog = catalog();
+        let value = ::serde_json::to_value(&catalog).unwrap();
+        assert_eq!(value, ::serde_json::from_str::<::serde_json::Value>(&catalog.render()).unwrap());
+        let loaded = ::serde_json::from_str::<ItemCatalog>("[{ \"id\": 7, \"name\": \"ice\", \"temperature\": \"frozen\" }]").unwrap();
+        assert_eq!(loaded.get(7).unwrap().temperature, Temperature::Frozen);
+        assert_eq!(loaded.weight(7), 0);
+    }
+}
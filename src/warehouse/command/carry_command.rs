@@ -0,0 +1,210 @@
+use warehouse::command::Command;
+use warehouse::object::{ Bot, Shelf, Object };
+use warehouse::{ World, SnapshotContext, Error, quote };
+#[cfg(feature = "serde")]
+use warehouse::command::command::field;
+
+use std::sync::{ Arc, Mutex };
+
+pub struct LiftCommand {
+    bot: Arc<Mutex<Bot>>,
+    shelf: Arc<Mutex<Shelf>>,
+    world: Arc<Mutex<World>>
+}
+
+impl LiftCommand {
+    pub fn new(bot: Arc<Mutex<Bot>>, shelf: Arc<Mutex<Shelf>>, world: Arc<Mutex<World>>) -> Box<Self> {
+        Box::new(Self {
+            bot: bot,
+            shelf: shelf,
+            world: world
+        })
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_checkpoint(value: &::serde_json::Value, context: &SnapshotContext) -> Result<Box<Self>, Error> {
+        Ok(Box::new(Self {
+            bot: context.bot(&field::<String>(value, "bot")?)?,
+            shelf: context.shelf(&field::<String>(value, "shelf")?)?,
+            world: context.world()
+        }))
+    }
+}
+
+impl Command for LiftCommand {
+    fn initialize(&mut self) -> Result<(), Error> {
+        let mut bot = self.bot.lock().unwrap();
+        let mut shelf = self.shelf.lock().unwrap();
+        if bot.location() != shelf.location() {
+            return Err(Error::FarAway { from: *bot.location(), to: *shelf.location() });
+        }
+        if let Some(carried) = self.world.lock().unwrap().carried(bot.id()) {
+            return Err(Error::AlreadyCarrying { id: bot.id().to_owned(), shelf: carried });
+        }
+        bot.lock()?;
+        if let Err(err) = shelf.lock() {
+            bot.unlock().unwrap();
+            return Err(err);
+        }
+        Ok(())
+    }
+    fn consume(&mut self) -> Result<bool, Error> {
+        let mut world = self.world.lock().unwrap();
+        let mut bot = self.bot.lock().unwrap();
+        let location = *self.shelf.lock().unwrap().location();
+        if *bot.location() != location {
+            return Err(Error::FarAway { from: *bot.location(), to: location });
+        }
+        world.lift(bot.id(), self.shelf.clone())?;
+        bot.unlock()?;
+        Ok(false)
+    }
+    fn render(&self) -> Result<String, Error> {
+        let bot = self.bot.lock().unwrap();
+        let shelf = self.shelf.lock().unwrap();
+        Ok(format!("{{ \"type\": \"{}\", \"bot\": {}, \"shelf\": {} }}", "lift", quote(bot.id()), quote(shelf.id())))
+    }
+    fn abort(&mut self) {
+        self.bot.lock().unwrap().unlock().ok();
+        self.shelf.lock().unwrap().unlock().ok();
+    }
+    fn objects(&self) -> Option<Vec<String>> {
+        Some(vec![self.bot.lock().unwrap().id().to_owned(), self.shelf.lock().unwrap().id().to_owned()])
+    }
+    fn snapshot(&self, context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        Ok(Box::new(Self {
+            bot: context.bot(self.bot.lock().unwrap().id())?,
+            shelf: context.shelf(self.shelf.lock().unwrap().id())?,
+            world: context.world()
+        }))
+    }
+}
+
+pub struct DropCommand {
+    bot: Arc<Mutex<Bot>>,
+    world: Arc<Mutex<World>>
+}
+
+impl DropCommand {
+    pub fn new(bot: Arc<Mutex<Bot>>, world: Arc<Mutex<World>>) -> Box<Self> {
+        Box::new(Self {
+            bot: bot,
+            world: world
+        })
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_checkpoint(value: &::serde_json::Value, context: &SnapshotContext) -> Result<Box<Self>, Error> {
+        Ok(Box::new(Self {
+            bot: context.bot(&field::<String>(value, "bot")?)?,
+            world: context.world()
+        }))
+    }
+}
+
+impl Command for DropCommand {
+    fn initialize(&mut self) -> Result<(), Error> {
+        let mut bot = self.bot.lock().unwrap();
+        if self.world.lock().unwrap().carried(bot.id()).is_none() {
+            return Err(Error::NotCarrying { id: bot.id().to_owned() });
+        }
+        bot.lock()
+    }
+    fn consume(&mut self) -> Result<bool, Error> {
+        let mut bot = self.bot.lock().unwrap();
+        let shelf = self.world.lock().unwrap().lower(bot.id())?;
+        shelf.lock().unwrap().unlock()?;
+        bot.unlock()?;
+        Ok(false)
+    }
+    fn render(&self) -> Result<String, Error> {
+        let bot = self.bot.lock().unwrap();
+        Ok(format!("{{ \"type\": \"{}\", \"bot\": {} }}", "drop", quote(bot.id())))
+    }
+    fn abort(&mut self) {
+        self.bot.lock().unwrap().unlock().ok();
+    }
+    fn objects(&self) -> Option<Vec<String>> {
+        Some(vec![self.bot.lock().unwrap().id().to_owned()])
+    }
+    fn snapshot(&self, context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        Ok(Box::new(Self {
+            bot: context.bot(self.bot.lock().unwrap().id())?,
+            world: context.world()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::object::Location;
+    use warehouse::command::BotPathMoveCommand;
+
+    fn setup() -> (Arc<Mutex<World>>, Arc<Mutex<Bot>>, Arc<Mutex<Shelf>>) {
+        let world = Arc::new(Mutex::new(World::new()));
+        let (bot, shelf) = (Bot::new(), Shelf::new());
+        *shelf.lock().unwrap().get_location() = Location::new(1, 0, 0);
+        world.lock().unwrap().add_items(vec![bot.clone(), shelf.clone()]);
+        (world, bot, shelf)
+    }
+
+    fn run(command: &mut Command) {
+        command.initialize().unwrap();
+        while command.consume().unwrap() {
+            command.render().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_lift() {
+        let (world, bot, shelf) = setup();
+        run(&mut *BotPathMoveCommand::new(bot.clone(), Location::new(1, 0, 0), world.clone()));
+        run(&mut *LiftCommand::new(bot.clone(), shelf.clone(), world.clone()));
+        let id = shelf.lock().unwrap().id().to_owned();
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(1, 0, 0));
+        assert_eq!(world.lock().unwrap().carried(bot.lock().unwrap().id()), Some(id));
+        assert_eq!(world.lock().unwrap().check_location(Location::new(1, 0, 0)), 2);
+        assert!(!*bot.lock().unwrap().get_lock());
+        assert!(*shelf.lock().unwrap().get_lock());
+    }
+
+    #[test]
+    fn test_carry() {
+        let (world, bot, shelf) = setup();
+        run(&mut *BotPathMoveCommand::new(bot.clone(), Location::new(1, 0, 0), world.clone()));
+        run(&mut *LiftCommand::new(bot.clone(), shelf.clone(), world.clone()));
+        run(&mut *BotPathMoveCommand::new(bot.clone(), Location::new(3, 2, 0), world.clone()));
+        run(&mut *DropCommand::new(bot.clone(), world.clone()));
+        assert_eq!(*shelf.lock().unwrap().location(), Location::new(3, 2, 0));
+        assert!(!*shelf.lock().unwrap().get_lock());
+        let world = world.lock().unwrap();
+        assert_eq!(world.check_location(Location::new(1, 0, 0)), 0);
+        assert_eq!(world.check_location(Location::new(3, 2, 0)), 2);
+        assert_eq!(world.carried(bot.lock().unwrap().id()), None);
+        assert_eq!(world.metrics().total().distance, 5);
+    }
+
+    #[test]
+    #[should_panic(expected="FarAway")]
+    fn test_lift_far() {
+        let (world, bot, shelf) = setup();
+        *shelf.lock().unwrap().get_location() = Location::new(2, 0, 0);
+        LiftCommand::new(bot, shelf, world).initialize().unwrap();
+    }
+
+    #[test]
+    fn test_lift_adjacent() {
+        let (world, bot, shelf) = setup();
+        let mut command = LiftCommand::new(bot.clone(), shelf.clone(), world.clone());
+        assert_eq!(command.initialize(), Err(Error::FarAway { from: Location::new(0, 0, 0), to: Location::new(1, 0, 0) }));
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(0, 0, 0));
+        assert!(!*bot.lock().unwrap().get_lock());
+        assert_eq!(world.lock().unwrap().carried(bot.lock().unwrap().id()), None);
+    }
+
+    #[test]
+    #[should_panic(expected="NotCarrying")]
+    fn test_drop_empty() {
+        let (world, bot, _) = setup();
+        DropCommand::new(bot, world).initialize().unwrap();
+    }
+}
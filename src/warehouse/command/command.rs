@@ -1,5 +1,84 @@
+use warehouse::{ SnapshotContext, Error };
+#[cfg(feature = "serde")]
+use warehouse::command::{ ParallelCommandQueue, SequentialCommandQueue, BotMoveCommand, BotPathMoveCommand, PickCommand, TransferCommand,
+    LiftCommand, DropCommand };
+#[cfg(feature = "serde")]
+use serde::{ Serialize, Serializer };
+#[cfg(feature = "serde")]
+use serde::ser;
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use serde_json::{ self, Value };
+
 pub trait Command {
-    fn initialize(&mut self) -> Result<(), &'static str>;
-    fn consume(&mut self) -> Result<bool, &'static str>;
-    fn render(&self) -> Result<String, &'static str>;
+    fn initialize(&mut self) -> Result<(), Error>;
+    fn consume(&mut self) -> Result<bool, Error>;
+    fn render(&self) -> Result<String, Error>;
+    #[cfg(feature = "serde")]
+    fn to_value(&self) -> Result<Value, Error> {
+        serde_json::from_str(&self.render()?).map_err(|_| Error::Other("command rendered invalid json"))
+    }
+    fn abort(&mut self) {}
+    fn orders(&self) -> Vec<String> {
+        Vec::new()
+    }
+    fn objects(&self) -> Option<Vec<String>> {
+        None
+    }
+    fn snapshot(&self, _context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        Err(Error::Other("command does not support snapshots"))
+    }
+    #[cfg(feature = "serde")]
+    fn checkpoint(&self) -> Result<Value, Error> {
+        self.to_value()
+    }
+}
+
+#[cfg(feature = "serde")]
+pub fn field<T: DeserializeOwned>(value: &Value, key: &str) -> Result<T, Error> {
+    value.get(key)
+        .ok_or(Error::Checkpoint(format!("missing field {}", key)))
+        .and_then(|value| serde_json::from_value(value.clone()).map_err(|err| Error::Checkpoint(format!("{}: {}", key, err))))
+}
+
+pub fn snapshot_commands(queue: &Vec<Box<Command>>, context: &SnapshotContext) -> Result<Vec<Box<Command>>, Error> {
+    queue.iter().map(|command| command.snapshot(context)).collect()
+}
+
+#[cfg(feature = "serde")]
+pub fn checkpoint_commands(queue: &Vec<Box<Command>>) -> Result<Vec<Value>, Error> {
+    queue.iter().map(|command| command.checkpoint()).collect()
+}
+
+#[cfg(feature = "serde")]
+pub fn restore_commands(value: &Value, context: &SnapshotContext) -> Result<Vec<Box<Command>>, Error> {
+    value.get("commands")
+        .and_then(Value::as_array)
+        .ok_or(Error::Checkpoint("missing field commands".to_owned()))?
+        .iter()
+        .map(|command| from_checkpoint(command, context))
+        .collect()
+}
+
+#[cfg(feature = "serde")]
+pub fn from_checkpoint(value: &Value, context: &SnapshotContext) -> Result<Box<Command>, Error> {
+    match value.get("type").and_then(Value::as_str) {
+        Some("parallel_queue") => Ok(ParallelCommandQueue::from_checkpoint(value, context)?),
+        Some("sequential_queue") => Ok(SequentialCommandQueue::from_checkpoint(value, context)?),
+        Some("move") => Ok(BotMoveCommand::from_checkpoint(value, context)?),
+        Some("path_move") => Ok(BotPathMoveCommand::from_checkpoint(value, context)?),
+        Some("pick") | Some("put") => Ok(PickCommand::from_checkpoint(value, context)?),
+        Some("transfer") => Ok(TransferCommand::from_checkpoint(value, context)?),
+        Some("lift") => Ok(LiftCommand::from_checkpoint(value, context)?),
+        Some("drop") => Ok(DropCommand::from_checkpoint(value, context)?),
+        kind => Err(Error::Checkpoint(format!("unknown command {}", kind.unwrap_or("without type"))))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Command {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        self.to_value().map_err(ser::Error::custom)?.serialize(serializer)
+    }
 }
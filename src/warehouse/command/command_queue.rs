@@ -1,6 +1,7 @@
 use warehouse::command::Command;
+use warehouse::Error;
 
 pub trait CommandQueue : Command {
-    fn schedule(&mut self, command: Box<Command>) -> Result<(), &'static str>;
+    fn schedule(&mut self, command: Box<Command>) -> Result<(), Error>;
     fn commands(&self) -> &Vec<Box<Command>>;
 }
@@ -6,18 +6,26 @@ mod test_command;
 mod panic_command;
 mod transfer_command;
 mod move_command;
+mod path_command;
 mod pick_command;
+mod carry_command;
+mod policy;
 
 pub use self::command::Command;
 pub use self::command_queue::CommandQueue;
-pub use self::parallel_queue::ParallelCommandQueue;
+pub use self::parallel_queue::{ ParallelCommandQueue, Failure };
 pub use self::sequential_queue::SequentialCommandQueue;
 pub use self::panic_command::ConsumePanicCommand;
 pub use self::transfer_command::BotTransferToCommand;
 pub use self::transfer_command::BotTransferFromCommand;
 pub use self::move_command::BotMoveCommand;
-pub use self::pick_command::SitePickCommand;
+pub use self::path_command::BotPathMoveCommand;
+pub use self::pick_command::{ SitePickCommand, PickCommand };
+pub use self::transfer_command::TransferCommand;
+pub use self::carry_command::{ LiftCommand, DropCommand };
+pub use self::policy::{ FailurePolicy, Attempts, Outcome };
 
 pub use self::test_command::TestCommand;
 pub use self::test_command::TestNextCommand;
+pub use self::test_command::TestFailCommand;
 pub use self::panic_command::PanicCommand;
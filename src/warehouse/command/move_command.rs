@@ -1,13 +1,17 @@
 use warehouse::command::Command;
 use warehouse::object::{ Bot, Location, Object };
-use warehouse::World;
+use warehouse::{ World, SnapshotContext, WAIT_LIMIT, Error, quote };
+#[cfg(feature = "serde")]
+use warehouse::command::command::field;
 
 use std::sync::{ Arc, Mutex };
 
 pub struct BotMoveCommand {
     bot: Arc<Mutex<Bot>>,
     location: Location,
-    world: Arc<Mutex<World>>
+    world: Arc<Mutex<World>>,
+    depart: Option<u64>,
+    waited: u32
 }
 
 impl BotMoveCommand {
@@ -15,36 +19,142 @@ impl BotMoveCommand {
         Box::new(Self {
             bot: bot,
             location: location,
-            world: world
+            world: world,
+            depart: None,
+            waited: 0
         })
     }
+    #[cfg(feature = "serde")]
+    pub fn from_checkpoint(value: &::serde_json::Value, context: &SnapshotContext) -> Result<Box<Self>, Error> {
+        Ok(Box::new(Self {
+            bot: context.bot(&field::<String>(value, "bot")?)?,
+            location: field(value, "location")?,
+            world: context.world(),
+            depart: field(value, "depart")?,
+            waited: match value.get("waited") {
+                Some(_) => field(value, "waited")?,
+                None => 0
+            }
+        }))
+    }
+    fn reserve(&mut self) -> Result<(), Error> {
+        let (id, from) = {
+            let bot = self.bot.lock().unwrap();
+            (bot.id().to_owned(), *bot.location())
+        };
+        let mut world = self.world.lock().unwrap();
+        world.release(&id);
+        world.reserve_edge(&id, from, self.location)?;
+        let depart = world.find_departure(&id, from, self.location)? + world.check_step(from, self.location)? - 1;
+        if depart > world.tick() && !world.grid().can_stop(from) {
+            return Err(Error::NoStop { location: from });
+        }
+        let mut path = vec![from; (depart - world.tick()) as usize];
+        path.push(self.location);
+        world.reserve_path(&id, from, &path)?;
+        self.depart = Some(depart);
+        Ok(())
+    }
+    fn wait(&mut self, err: Error) -> Result<bool, Error> {
+        self.waited += 1;
+        if self.waited < WAIT_LIMIT {
+            return Ok(true);
+        }
+        let mut bot = self.bot.lock().unwrap();
+        self.world.lock().unwrap().release(bot.id());
+        bot.unlock()?;
+        Err(err)
+    }
 }
 
 impl Command for BotMoveCommand {
-    fn initialize(&mut self) -> Result<(), &'static str> {
-        let mut bot = self.bot.lock().unwrap();
-        if !self.location.nearby(*bot.location()) {
-            return Err("target location far away")
+    fn initialize(&mut self) -> Result<(), Error> {
+        {
+            let mut bot = self.bot.lock().unwrap();
+            if !self.location.nearby(*bot.location()) {
+                return Err(Error::FarAway { from: *bot.location(), to: self.location })
+            }
+            let mut world = self.world.lock().unwrap();
+            world.check_step(*bot.location(), self.location)?;
+            bot.lock()?;
+            if let Err(err) = world.reserve_edge(bot.id(), *bot.location(), self.location) {
+                world.get_metrics().record_failed(bot.id());
+                bot.unlock().unwrap();
+                return Err(err);
+            }
         }
-        bot.lock()
+        if self.reserve().is_err() {
+            let id = self.bot.lock().unwrap().id().to_owned();
+            self.world.lock().unwrap().get_metrics().record_failed(&id);
+        }
+        Ok(())
     }
-    fn consume(&mut self) -> Result<bool, &'static str> {
-        let mut world = self.world.lock().unwrap();
-        if world.check_location(self.location) > 0 {
-            let mut bot = self.bot.lock().unwrap();
-            bot.unlock().unwrap();
-            return Err("target location not available");
+    fn consume(&mut self) -> Result<bool, Error> {
+        if self.depart.is_none() {
+            if let Err(err) = self.reserve() {
+                let id = self.bot.lock().unwrap().id().to_owned();
+                self.world.lock().unwrap().get_metrics().record_failed(&id);
+                return self.wait(err);
+            }
+            self.waited = 0;
         }
-        world.notify_will_move(self.bot.clone(), self.location).unwrap();
+        let blocked = {
+            let mut world = self.world.lock().unwrap();
+            if world.tick() < self.depart.unwrap() {
+                return Ok(true);
+            }
+            let id = self.bot.lock().unwrap().id().to_owned();
+            match world.check_passage(&id, self.location) {
+                0 => None,
+                _ => {
+                    world.get_metrics().record_blocked(self.bot.lock().unwrap().id());
+                    Some(Error::LocationOccupied {
+                        location: self.location,
+                        by: world.occupant(self.location).unwrap_or("unknown".to_owned())
+                    })
+                }
+            }
+        };
+        if let Some(err) = blocked {
+            self.depart = None;
+            return self.wait(err);
+        }
+        let mut world = self.world.lock().unwrap();
+        world.notify_will_move(self.bot.clone(), self.location)?;
         let mut bot = self.bot.lock().unwrap();
+        bot.unlock()?;
         *bot.get_location() = self.location;
-        bot.unlock().unwrap();
+        world.release(bot.id());
         Ok(false)
     }
-    fn render(&self) -> Result<String, &'static str> {
+    fn render(&self) -> Result<String, Error> {
         let bot = self.bot.lock().unwrap();
-        Ok(format!("{{ \"type\": \"{}\", \"bot\": \"{}\", \"location\": {} }}", 
-            "move", bot.id(), self.location.render()))
+        Ok(format!("{{ \"type\": \"{}\", \"bot\": {}, \"location\": {} }}", 
+            "move", quote(bot.id()), self.location.render()))
+    }
+    fn abort(&mut self) {
+        let mut bot = self.bot.lock().unwrap();
+        self.world.lock().unwrap().release(bot.id());
+        bot.unlock().ok();
+    }
+    fn objects(&self) -> Option<Vec<String>> {
+        Some(vec![self.bot.lock().unwrap().id().to_owned()])
+    }
+    fn snapshot(&self, context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        Ok(Box::new(Self {
+            bot: context.bot(self.bot.lock().unwrap().id())?,
+            location: self.location,
+            world: context.world(),
+            depart: self.depart,
+            waited: self.waited
+        }))
+    }
+    #[cfg(feature = "serde")]
+    fn checkpoint(&self) -> Result<::serde_json::Value, Error> {
+        let mut value = self.to_value()?;
+        value["depart"] = json!(self.depart);
+        value["waited"] = json!(self.waited);
+        Ok(value)
     }
 }
 
@@ -55,8 +165,15 @@ mod tests {
     use warehouse::World;
     use warehouse::command::{ CommandQueue, ParallelCommandQueue };
 
+    fn run(command: &mut Command, world: &Arc<Mutex<World>>) -> Result<(), Error> {
+        while command.consume()? {
+            world.lock().unwrap().advance_tick();
+        }
+        Ok(())
+    }
+
     #[test]
-    #[should_panic(expected="target location far away")]
+    #[should_panic(expected="FarAway")]
     fn test_initialize() {
         let world = Arc::new(Mutex::new(World::new()));
         let bot = Bot::new();
@@ -67,6 +184,7 @@ mod tests {
     #[test]
     fn test_initialize_lock() {
         let world = Arc::new(Mutex::new(World::new()));
+        world.lock().unwrap().add_elevator(0, 0, 1);
         let bot = Bot::new();
         let mut cmd = BotMoveCommand::new(bot.clone(), Location::new(0, 0, 1), world);
         cmd.initialize().unwrap();
@@ -78,6 +196,7 @@ mod tests {
         let world = Arc::new(Mutex::new(World::new()));
         let bot = Bot::new();
         world.lock().unwrap().add_items(vec![bot.clone()]);
+        world.lock().unwrap().add_elevator(0, 0, 1);
         let mut cmd = BotMoveCommand::new(bot.clone(), Location::new(0, 0, 1), world);
         cmd.initialize().unwrap();
         assert!(!cmd.consume().unwrap());
@@ -85,59 +204,190 @@ mod tests {
         assert!(!*bot.lock().unwrap().get_lock());
     }
     #[test]
-    #[should_panic(expected="target location not available")]
+    #[should_panic(expected="NoElevator")]
+    fn test_initialize_climb() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::new();
+        world.lock().unwrap().add_elevator(1, 0, 1);
+        BotMoveCommand::new(bot, Location::new(0, 0, 1), world).initialize().unwrap();
+    }
+    #[test]
+    fn test_consume_elevator() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::new();
+        world.lock().unwrap().add_items(vec![bot.clone()]);
+        world.lock().unwrap().add_elevator(0, 0, 3);
+        let mut cmd = BotMoveCommand::new(bot.clone(), Location::new(0, 0, 1), world.clone());
+        cmd.initialize().unwrap();
+        for _ in 0..2 {
+            assert!(cmd.consume().unwrap());
+            world.lock().unwrap().advance_tick();
+        }
+        assert!(!cmd.consume().unwrap());
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(0, 0, 1));
+    }
+    #[test]
+    fn test_initialize_grid() {
+        use warehouse::GridMap;
+        use warehouse::object::Direction;
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::new();
+        world.lock().unwrap().set_grid(GridMap::with_size(Location::new(1, 1, 1)));
+        assert_eq!(Location::new(0, 0, 0).step(Direction::West), None);
+        for direction in &[Direction::East, Direction::South, Direction::Up] {
+            let location = Location::new(0, 0, 0).step(*direction).unwrap();
+            let mut cmd = BotMoveCommand::new(bot.clone(), location, world.clone());
+            assert_eq!(cmd.initialize(), Err(Error::OutOfBounds { location: location }));
+        }
+        assert!(!*bot.lock().unwrap().get_lock());
+    }
+    #[test]
+    #[should_panic(expected="NoStop")]
+    fn test_consume_no_stop() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::new();
+        world.lock().unwrap().add_items(vec![bot.clone()]);
+        world.lock().unwrap().get_grid().add_no_stop(Location::new(0, 0, 0));
+        world.lock().unwrap().reserve_path("bot-other", Location::new(2, 0, 0), &vec![Location::new(1, 0, 0), Location::new(2, 0, 0)]).unwrap();
+        let mut cmd = BotMoveCommand::new(bot, Location::new(1, 0, 0), world);
+        cmd.initialize().unwrap();
+        while cmd.consume().unwrap() {}
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::new();
+        let cmd = BotMoveCommand::new(bot, Location::new(0, 0, 1), world);
+        assert_eq!(cmd.to_value().unwrap(), ::serde_json::from_str::<::serde_json::Value>(&cmd.render().unwrap()).unwrap());
+    }
+    #[test]
+    fn test_consume_release() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::new();
+        world.lock().unwrap().add_items(vec![bot.clone()]);
+        world.lock().unwrap().add_elevator(0, 0, 1);
+        let mut cmd = BotMoveCommand::new(bot.clone(), Location::new(0, 0, 1), world.clone());
+        cmd.initialize().unwrap();
+        assert_eq!(world.lock().unwrap().reservations().owner(Location::new(0, 0, 1), 1), Some(bot.lock().unwrap().id()));
+        cmd.consume().unwrap();
+        assert_eq!(world.lock().unwrap().reservations().owner(Location::new(0, 0, 1), 1), None);
+    }
+    #[test]
+    #[should_panic(expected="LocationOccupied")]
     fn test_consume_collision_1() {
         let world = Arc::new(Mutex::new(World::new()));
-        let mut target_loc = Location::new(0, 0, 0);
         let (bot1, bot2) = (Bot::new(), Bot::new());
-        {
-            let (mut bot1, mut bot2) = (bot1.lock().unwrap(),bot2.lock().unwrap());
-            let (loc1, loc2) = (bot1.get_location(), bot2.get_location());
-            *loc2 = (*loc2).front();
-            target_loc = (*loc1).front();
-        }
+        *bot2.lock().unwrap().get_location() = Location::new(0, 1, 0);
         world.lock().unwrap().add_items(vec![bot1.clone(), bot2.clone()]);
-        let mut cmd = BotMoveCommand::new(bot1.clone(), target_loc, world);
+        let mut cmd = BotMoveCommand::new(bot1.clone(), Location::new(0, 1, 0), world.clone());
         cmd.initialize().unwrap();
-        assert!(!cmd.consume().unwrap());
+        run(&mut *cmd, &world).unwrap();
     }
     #[test]
-    #[should_panic(expected="target location not available")]
     fn test_consume_collision_2() {
         let world = Arc::new(Mutex::new(World::new()));
-        let mut target_loc = Location::new(0, 0, 0);
+        let target = Location::new(0, 1, 0);
         let (bot1, bot2) = (Bot::new(), Bot::new());
-        {
-            let (mut bot1, mut bot2) = (bot1.lock().unwrap(),bot2.lock().unwrap());
-            let (loc1, loc2) = (bot1.get_location(), bot2.get_location());
-            *loc2 = (*loc2).front().front();
-            target_loc = (*loc1).front();
-        }
+        *bot2.lock().unwrap().get_location() = Location::new(0, 2, 0);
         world.lock().unwrap().add_items(vec![bot1.clone(), bot2.clone()]);
         let mut queue = ParallelCommandQueue::new();
         queue.initialize().unwrap();
-        queue.schedule(BotMoveCommand::new(bot1.clone(), target_loc, world.clone())).unwrap();
-        queue.schedule(BotMoveCommand::new(bot2.clone(), target_loc, world.clone())).unwrap();
-        queue.consume().unwrap();
+        queue.schedule(BotMoveCommand::new(bot1.clone(), target, world.clone())).unwrap();
+        queue.schedule(BotMoveCommand::new(bot2.clone(), target, world.clone())).unwrap();
+        let by = bot1.lock().unwrap().id().to_owned();
+        assert_eq!(run(&mut queue, &world), Err(Error::LocationOccupied { location: target, by: by }));
+        assert_eq!(*bot1.lock().unwrap().location(), target);
+        assert_eq!(*bot2.lock().unwrap().location(), Location::new(0, 2, 0));
+        assert!(!*bot2.lock().unwrap().get_lock());
+        assert_eq!(world.lock().unwrap().check_location(target), 1);
     }
     #[test]
-    #[should_panic(expected="target location not available")]
     fn test_consume_collision_3() {
         let world = Arc::new(Mutex::new(World::new()));
-        let (mut target_loc_1, mut target_loc_2) = (Location::new(0, 0, 0), Location::new(0, 0, 0));
+        let (loc1, loc2) = (Location::new(0, 0, 0), Location::new(0, 1, 0));
         let (bot1, bot2) = (Bot::new(), Bot::new());
-        {
-            let (mut bot1, mut bot2) = (bot1.lock().unwrap(),bot2.lock().unwrap());
-            let (loc1, loc2) = (bot1.get_location(), bot2.get_location());
-            *loc2 = (*loc2).front();
-            target_loc_1 = *loc2;
-            target_loc_2 = *loc1;
-        }
+        *bot2.lock().unwrap().get_location() = loc2;
+        world.lock().unwrap().add_items(vec![bot1.clone(), bot2.clone()]);
+        let mut queue = ParallelCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule(BotMoveCommand::new(bot1.clone(), loc2, world.clone())).unwrap();
+        let by = bot1.lock().unwrap().id().to_owned();
+        assert_eq!(queue.schedule(BotMoveCommand::new(bot2.clone(), loc1, world.clone())), Err(Error::SwapConflict { location: loc1, by: by }));
+        assert!(!*bot2.lock().unwrap().get_lock());
+        let by = bot2.lock().unwrap().id().to_owned();
+        assert_eq!(run(&mut queue, &world), Err(Error::LocationOccupied { location: loc2, by: by }));
+        assert_eq!(*bot1.lock().unwrap().location(), loc1);
+        assert_eq!(*bot2.lock().unwrap().location(), loc2);
+        assert_eq!(world.lock().unwrap().check_location(loc1), 1);
+        assert_eq!(world.lock().unwrap().check_location(loc2), 1);
+    }
+    #[test]
+    fn test_consume_follow() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let (bot1, bot2) = (Bot::new(), Bot::new());
+        *bot1.lock().unwrap().get_location() = Location::new(0, 1, 0);
         world.lock().unwrap().add_items(vec![bot1.clone(), bot2.clone()]);
         let mut queue = ParallelCommandQueue::new();
         queue.initialize().unwrap();
-        queue.schedule(BotMoveCommand::new(bot1.clone(), target_loc_1, world.clone())).unwrap();
-        queue.schedule(BotMoveCommand::new(bot2.clone(), target_loc_2, world.clone())).unwrap();
-        queue.consume().unwrap();
+        queue.schedule(BotMoveCommand::new(bot1.clone(), Location::new(0, 2, 0), world.clone())).unwrap();
+        queue.schedule(BotMoveCommand::new(bot2.clone(), Location::new(0, 1, 0), world.clone())).unwrap();
+        assert!(!queue.consume().unwrap());
+        assert_eq!(*bot1.lock().unwrap().location(), Location::new(0, 2, 0));
+        assert_eq!(*bot2.lock().unwrap().location(), Location::new(0, 1, 0));
+        assert_eq!(world.lock().unwrap().check_location(Location::new(0, 1, 0)), 1);
+    }
+    #[test]
+    #[should_panic(expected="SwapConflict")]
+    fn test_initialize_swap() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::new();
+        *bot.lock().unwrap().get_location() = Location::new(0, 1, 0);
+        world.lock().unwrap().reserve_path("bot-other", Location::new(0, 0, 0), &vec![Location::new(0, 1, 0), Location::new(0, 2, 0)]).unwrap();
+        let mut cmd = BotMoveCommand::new(bot.clone(), Location::new(0, 0, 0), world.clone());
+        cmd.initialize().unwrap();
+        assert!(*bot.lock().unwrap().get_lock());
+        while cmd.consume().unwrap() {}
+    }
+    #[test]
+    fn test_initialize_failed_metrics() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let (bot1, bot2) = (Bot::new(), Bot::new());
+        *bot2.lock().unwrap().get_location() = Location::new(0, 1, 0);
+        world.lock().unwrap().add_items(vec![bot1.clone(), bot2.clone()]);
+        let mut cmd = BotMoveCommand::new(bot1.clone(), Location::new(0, 1, 0), world.clone());
+        cmd.initialize().unwrap();
+        assert_eq!(world.lock().unwrap().metrics().bot(bot1.lock().unwrap().id()).unwrap().failed, 1);
+        assert!(*bot1.lock().unwrap().get_lock());
+    }
+    #[test]
+    fn test_consume_wait_limit() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let (bot1, bot2) = (Bot::new(), Bot::new());
+        *bot2.lock().unwrap().get_location() = Location::new(0, 1, 0);
+        world.lock().unwrap().add_items(vec![bot1.clone(), bot2.clone()]);
+        let mut cmd = BotMoveCommand::new(bot1.clone(), Location::new(0, 1, 0), world.clone());
+        cmd.initialize().unwrap();
+        for _ in 1..WAIT_LIMIT {
+            assert!(cmd.consume().unwrap());
+        }
+        assert!(cmd.consume().is_err());
+        assert!(!*bot1.lock().unwrap().get_lock());
+        assert!(world.lock().unwrap().reservations().free_from(bot1.lock().unwrap().id(), Location::new(0, 1, 0)).is_some());
+    }
+    #[test]
+    fn test_consume_wait() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let (bot1, bot2) = (Bot::new(), Bot::new());
+        *bot2.lock().unwrap().get_location() = Location::new(1, 1, 0);
+        world.lock().unwrap().add_items(vec![bot1.clone(), bot2.clone()]);
+        world.lock().unwrap().reserve_path("bot-other", Location::new(2, 0, 0), &vec![Location::new(1, 0, 0), Location::new(0, 0, 1)]).unwrap();
+        let mut cmd = BotMoveCommand::new(bot2.clone(), Location::new(1, 0, 0), world.clone());
+        cmd.initialize().unwrap();
+        assert!(cmd.consume().unwrap());
+        assert_eq!(*bot2.lock().unwrap().location(), Location::new(1, 1, 0));
+        world.lock().unwrap().advance_tick();
+        assert!(!cmd.consume().unwrap());
+        assert_eq!(*bot2.lock().unwrap().location(), Location::new(1, 0, 0));
     }
 }
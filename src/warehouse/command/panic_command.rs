@@ -1,4 +1,5 @@
 use super::command::Command;
+use warehouse::Error;
 
 pub struct PanicCommand {
 }
@@ -10,13 +11,13 @@ impl PanicCommand {
 }
 
 impl Command for PanicCommand {
-    fn initialize(&mut self) -> Result<(), &'static str> {
-        Err("panic command initialized")
+    fn initialize(&mut self) -> Result<(), Error> {
+        Err(Error::Other("panic command initialized"))
     }
-    fn consume(&mut self) -> Result<bool, &'static str> {
-        Err("panic command consumed")
+    fn consume(&mut self) -> Result<bool, Error> {
+        Err(Error::Other("panic command consumed"))
     }
-    fn render(&self) -> Result<String, &'static str> {
+    fn render(&self) -> Result<String, Error> {
         Ok("{ \"type\": \"panic\" }".to_owned())
     }
 }
@@ -31,13 +32,13 @@ impl ConsumePanicCommand {
 }
 
 impl Command for ConsumePanicCommand {
-    fn initialize(&mut self) -> Result<(), &'static str> {
+    fn initialize(&mut self) -> Result<(), Error> {
         Ok(())
     }
-    fn consume(&mut self) -> Result<bool, &'static str> {
-        Err("consume panic command consumed")
+    fn consume(&mut self) -> Result<bool, Error> {
+        Err(Error::Other("consume panic command consumed"))
     }
-    fn render(&self) -> Result<String, &'static str> {
+    fn render(&self) -> Result<String, Error> {
         Ok("{ \"type\": \"panicconsume\" }".to_owned())
     }
 }
@@ -1,27 +1,207 @@
-use warehouse::command::{ Command, CommandQueue };
+use warehouse::command::{ Command, CommandQueue, FailurePolicy, Attempts, Outcome };
+use warehouse::command::command::snapshot_commands;
+#[cfg(feature = "serde")]
+use warehouse::command::command::{ field, checkpoint_commands, restore_commands };
+use warehouse::{ SnapshotContext, Event, Error, quote };
 
 pub struct ParallelCommandQueue {
-    queue: Vec<Box<Command>>
+    queue: Vec<Box<Command>>,
+    attempts: Vec<Attempts>,
+    policy: FailurePolicy,
+    initialized: Vec<String>,
+    tick: u64,
+    failures: Vec<Failure>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    pub tick: u64,
+    pub command: String,
+    pub error: Error
+}
+
+impl Failure {
+    pub fn render(&self) -> String {
+        format!("{{ \"tick\": {}, \"command\": {}, \"error\": {} }}", self.tick, self.command, quote(&self.error))
+    }
 }
 
 impl ParallelCommandQueue {
     pub fn new() -> Self {
+        Self::with_policy(FailurePolicy::Escalate)
+    }
+    pub fn with_policy(policy: FailurePolicy) -> Self {
         Self {
-            queue: Vec::new()
+            queue: Vec::new(),
+            attempts: Vec::new(),
+            policy: policy,
+            initialized: Vec::new(),
+            tick: 0,
+            failures: Vec::new()
+        }
+    }
+    pub fn fork(&self, context: &SnapshotContext) -> Result<Self, Error> {
+        Ok(Self {
+            queue: snapshot_commands(&self.queue, context)?,
+            attempts: self.attempts.clone(),
+            policy: self.policy,
+            initialized: Vec::new(),
+            tick: self.tick,
+            failures: Vec::new()
+        })
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_checkpoint(value: &::serde_json::Value, context: &SnapshotContext) -> Result<Box<Self>, Error> {
+        let queue = restore_commands(value, context)?;
+        let policy = match value.get("policy") {
+            Some(_) => field(value, "policy")?,
+            None => FailurePolicy::Escalate
+        };
+        let attempts = match value.get("attempts") {
+            Some(_) => field::<Vec<Attempts>>(value, "attempts")?,
+            None => vec![Attempts::new(policy); queue.len()]
+        };
+        if attempts.len() != queue.len() {
+            return Err(Error::Checkpoint("attempts do not match commands".to_owned()));
+        }
+        Ok(Box::new(Self {
+            queue: queue,
+            attempts: attempts,
+            policy: policy,
+            initialized: Vec::new(),
+            tick: 0,
+            failures: Vec::new()
+        }))
+    }
+    pub fn schedule_with(&mut self, command: Box<Command>, policy: FailurePolicy) -> Result<(), Error> {
+        let mut command = command;
+        command.initialize()?;
+        self.initialized.push(command.render()?);
+        self.queue.push(command);
+        self.attempts.push(Attempts::new(policy));
+        Ok(())
+    }
+    pub fn attempts(&self) -> &Vec<Attempts> {
+        &self.attempts
+    }
+    fn consume_each(&mut self, failed: &[bool]) -> Vec<Result<bool, Error>> {
+        self.queue.iter_mut().zip(&self.attempts).zip(failed)
+            .map(|((command, attempts), &failed)| match attempts.waiting() || failed {
+                true => Ok(true),
+                false => command.consume()
+            })
+            .collect()
+    }
+    fn succeed(&mut self, results: &[Result<bool, Error>], failed: &[bool]) {
+        for ((attempts, result), &failed) in self.attempts.iter_mut().zip(results).zip(failed) {
+            if result.is_ok() && !failed && !attempts.waiting() {
+                attempts.succeed();
+            }
+        }
+    }
+    fn recover(&mut self, results: Vec<Result<bool, Error>>, tick: u64, failures: &mut Vec<Failure>) -> Result<Vec<bool>, Error> {
+        let mut keep = Vec::new();
+        for (idx, result) in results.into_iter().enumerate() {
+            let err = match result {
+                Ok(next) => {
+                    keep.push(next);
+                    continue;
+                },
+                Err(err) => err
+            };
+            failures.push(Failure { tick: tick, command: self.queue[idx].render()?, error: err.clone() });
+            match self.attempts[idx].fail(err) {
+                Outcome::Retry => keep.push(true),
+                Outcome::Skip => {
+                    self.queue[idx].abort();
+                    keep.push(false);
+                },
+                Outcome::Fail(err) => return Err(err)
+            }
+        }
+        Ok(keep)
+    }
+    fn retain(&mut self, keep: Vec<bool>) {
+        let (queue, attempts) = self.queue.drain(..).zip(self.attempts.drain(..)).zip(keep)
+            .filter(|&(_, keep)| keep)
+            .map(|(entry, _)| entry)
+            .unzip();
+        self.queue = queue;
+        self.attempts = attempts;
+    }
+    fn commit(&mut self, keep: Vec<bool>) {
+        self.retain(keep);
+        self.tick += 1;
+        for attempts in &mut self.attempts {
+            attempts.advance_tick();
+        }
+    }
+    pub fn take_failures(&mut self) -> Vec<Failure> {
+        self.failures.drain(..).collect()
+    }
+    pub fn consume_at(&mut self, tick: u64) -> Result<Vec<Failure>, Error> {
+        self.tick = tick;
+        self.initialized.clear();
+        let failed = vec![false; self.queue.len()];
+        let results = self.consume_each(&failed);
+        self.succeed(&results, &failed);
+        let mut failures = Vec::new();
+        let keep = self.recover(results, tick, &mut failures)?;
+        self.commit(keep);
+        Ok(failures)
+    }
+    pub fn consume_atomic(&mut self, context: &SnapshotContext) -> Result<Vec<Failure>, Error> {
+        let world = context.world();
+        self.tick = world.lock().unwrap().tick();
+        for command in self.initialized.drain(..) {
+            world.lock().unwrap().emit(Event::CommandInitialized { command: command });
+        }
+        let mut failures = Vec::new();
+        let mut failed = vec![false; self.queue.len()];
+        loop {
+            let transaction = context.begin(&self.queue)?;
+            let results = self.consume_each(&failed);
+            if results.iter().all(Result::is_ok) {
+                self.succeed(&results, &failed);
+                let keep = results.into_iter().map(Result::unwrap).collect::<Vec<bool>>();
+                for (command, _) in self.queue.iter().zip(&keep).filter(|&(_, keep)| !keep) {
+                    world.lock().unwrap().emit(Event::CommandCompleted { command: command.render()? });
+                }
+                self.commit(keep);
+                return Ok(failures);
+            }
+            let tick = self.tick;
+            self.queue = transaction.rollback(context);
+            for (failed, result) in failed.iter_mut().zip(&results) {
+                *failed = *failed || result.is_err();
+            }
+            let results = results.into_iter().map(|result| result.map(|_| true)).collect();
+            let reported = failures.len();
+            let keep = self.recover(results, tick, &mut failures);
+            for failure in &failures[reported..] {
+                world.lock().unwrap().emit(Event::CommandFailed { command: failure.command.clone(), error: failure.error.clone() });
+            }
+            let keep = keep?;
+            failed = failed.into_iter().zip(&keep).filter(|&(_, &keep)| keep).map(|(failed, _)| failed).collect();
+            self.retain(keep);
+        }
+    }
+    fn render_failing(&self) -> String {
+        let failing = self.attempts.iter().enumerate()
+            .filter(|&(_, attempts)| attempts.failures > 0)
+            .map(|(idx, attempts)| attempts.render(idx))
+            .collect::<Vec<String>>();
+        match failing.len() {
+            0 => "".to_owned(),
+            _ => format!(", \"failing\": [{}]", failing.join(", "))
         }
     }
 }
 
 impl CommandQueue for ParallelCommandQueue {
-    fn schedule(&mut self, command: Box<Command>) -> Result<(), &'static str> {
-        let mut command = command;
-        match command.initialize() {
-            Ok(_) => {
-                self.queue.push(command);
-                Ok(())
-            },
-            Err(err) => Err(err)
-        }
+    fn schedule(&mut self, command: Box<Command>) -> Result<(), Error> {
+        let policy = self.policy;
+        self.schedule_with(command, policy)
     }
     fn commands(&self) -> &Vec<Box<Command>> {
         &self.queue
@@ -29,27 +209,18 @@ impl CommandQueue for ParallelCommandQueue {
 }
 
 impl Command for ParallelCommandQueue {
-    fn initialize(&mut self) -> Result<(), &'static str> {
+    fn initialize(&mut self) -> Result<(), Error> {
         Ok(())
     }
 
-    fn consume(&mut self) -> Result<bool, &'static str> {
-        let mut _queue = Vec::new() as Vec<bool>;
-        for command in &mut self.queue {
-            match command.consume() {
-                Ok(next) => _queue.push(next),
-                Err(err) => return Err(err)
-            }
-        }
-        let mut idx = 0;
-        self.queue.retain(|_command| {
-            idx += 1;
-            _queue[idx - 1]
-        });
+    fn consume(&mut self) -> Result<bool, Error> {
+        let tick = self.tick;
+        let failures = self.consume_at(tick)?;
+        self.failures.extend(failures);
         Ok(self.queue.len() > 0)
     }
-    fn render(&self) -> Result<String, &'static str> {
-        let mut error_flag: Option<&'static str> = None;
+    fn render(&self) -> Result<String, Error> {
+        let mut error_flag: Option<Error> = None;
         let result = self.queue.iter()
             .map(|command: &Box<Command>| match command.render() {
                 Ok(result) => result,
@@ -62,9 +233,32 @@ impl Command for ParallelCommandQueue {
             .join(", ");
         match error_flag {
             Some(err) => Err(err),
-            None => Ok(format!("{{ \"type\": \"parallel_queue\", \"commands\": [{}] }}", result))
+            None => Ok(format!("{{ \"type\": \"parallel_queue\", \"commands\": [{}]{} }}", result, self.render_failing()))
+        }
+    }
+    fn abort(&mut self) {
+        for command in &mut self.queue {
+            command.abort();
         }
     }
+    fn orders(&self) -> Vec<String> {
+        self.queue.iter().flat_map(|command| command.orders()).collect()
+    }
+    fn objects(&self) -> Option<Vec<String>> {
+        self.queue.iter().map(|command| command.objects()).collect::<Option<Vec<Vec<String>>>>().map(|ids| ids.concat())
+    }
+    fn snapshot(&self, context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        Ok(Box::new(self.fork(context)?))
+    }
+    #[cfg(feature = "serde")]
+    fn checkpoint(&self) -> Result<::serde_json::Value, Error> {
+        Ok(json!({
+            "type": "parallel_queue",
+            "commands": checkpoint_commands(&self.queue)?,
+            "policy": self.policy,
+            "attempts": self.attempts
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -74,6 +268,11 @@ mod tests {
     use warehouse::command::PanicCommand;
     use warehouse::command::ConsumePanicCommand;
     use warehouse::command::TestNextCommand;
+    use warehouse::command::TestFailCommand;
+    use warehouse::command::{ BotMoveCommand, BotPathMoveCommand };
+    use warehouse::object::{ Bot, Location, Object };
+    use warehouse::{ World, OrderBook, EventLog };
+    use std::sync::{ Arc, Mutex };
 
     #[test]
     fn test_new() {
@@ -161,6 +360,191 @@ mod tests {
         assert!(queue.queue.len() == 1);
     }
 
+    #[test]
+    fn test_consume_atomic() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let (bot, stray) = (Bot::new(), Bot::new());
+        world.lock().unwrap().add_items(vec![bot.clone()]);
+        *stray.lock().unwrap().get_location() = Location::new(3, 3, 0);
+        let context = SnapshotContext::new(world.clone(), Arc::new(Mutex::new(OrderBook::new())), &[bot.clone(), stray.clone()], &[], &[]);
+        let log = EventLog::new();
+        world.lock().unwrap().subscribe(log.clone());
+        let mut queue = ParallelCommandQueue::with_policy(FailurePolicy::Skip);
+        queue.initialize().unwrap();
+        queue.schedule(BotMoveCommand::new(bot.clone(), Location::new(1, 0, 0), world.clone())).unwrap();
+        queue.schedule(BotMoveCommand::new(stray.clone(), Location::new(4, 3, 0), world.clone())).unwrap();
+        let failures = queue.consume_atomic(&context).unwrap();
+        let id = stray.lock().unwrap().id().to_owned();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].error, Error::NotInWorld { id: id.clone() });
+        assert_eq!(failures[0].render(), format!("{{ \"tick\": 0, \"command\": {{ \"type\": \"move\", \"bot\": \"{}\", \"location\": {{ \"x\": 4, \"y\": 3, \"z\": 0 }} }}, \"error\": \"object {} not in world\" }}", id, id));
+        assert_eq!(queue.commands().len(), 0);
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(1, 0, 0));
+        assert_eq!(*stray.lock().unwrap().location(), Location::new(3, 3, 0));
+        assert!(!*bot.lock().unwrap().get_lock());
+        assert!(!*stray.lock().unwrap().get_lock());
+        assert_eq!(world.lock().unwrap().metrics().total().distance, 1);
+        world.lock().unwrap().advance_tick();
+        let events = log.lock().unwrap().events.iter().map(|&(_, ref event)| event.clone()).collect::<Vec<Event>>();
+        let id = bot.lock().unwrap().id().to_owned();
+        assert_eq!(events.len(), 7);
+        assert_eq!(events[0], Event::ObjectLocked { id: id.clone() });
+        assert_eq!(events[3], Event::CommandFailed { command: failures[0].command.clone(), error: failures[0].error.clone() });
+        assert_eq!(events[4], Event::ObjectMoved { id: id.clone(), from: Location::new(0, 0, 0), to: Location::new(1, 0, 0) });
+        assert_eq!(events[5], Event::ObjectUnlocked { id: id });
+        match events[6] {
+            Event::CommandCompleted { ref command } => assert!(command.contains("\"move\"")),
+            ref event => panic!("unexpected event {:?}", event)
+        }
+    }
+
+    #[test]
+    fn test_consume_atomic_commit() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::new();
+        world.lock().unwrap().add_items(vec![bot.clone()]);
+        let context = SnapshotContext::new(world.clone(), Arc::new(Mutex::new(OrderBook::new())), &[bot.clone()], &[], &[]);
+        let mut queue = ParallelCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule(BotPathMoveCommand::new(bot.clone(), Location::new(2, 0, 0), world.clone())).unwrap();
+        assert_eq!(queue.consume_atomic(&context).unwrap(), vec![]);
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(1, 0, 0));
+        assert_eq!(queue.commands().len(), 1);
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut queue = ParallelCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule_with(Box::new(TestFailCommand::new(2)), FailurePolicy::Retry { after: 2, limit: 3 }).unwrap();
+        assert!(queue.consume().unwrap());
+        assert_eq!(queue.render().unwrap(), "{ \"type\": \"parallel_queue\", \"commands\": [{ \"type\": \"testfail\" }], \"failing\": [{ \"index\": 0, \"failures\": 1, \"error\": \"test command failed\" }] }");
+        assert!(queue.consume().unwrap());
+        assert_eq!(queue.attempts()[0].failures, 1);
+        assert!(queue.consume().unwrap());
+        assert_eq!(queue.attempts()[0].failures, 2);
+        assert!(queue.consume().unwrap());
+        assert!(!queue.consume().unwrap());
+    }
+
+    struct FlakyCommand {
+        ticks: u32
+    }
+
+    impl Command for FlakyCommand {
+        fn initialize(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+        fn consume(&mut self) -> Result<bool, Error> {
+            self.ticks += 1;
+            match self.ticks % 2 {
+                1 => Err(Error::Other("flaky command failed")),
+                _ => Ok(self.ticks < 6)
+            }
+        }
+        fn render(&self) -> Result<String, Error> {
+            Ok("{ \"type\": \"flaky\" }".to_owned())
+        }
+    }
+
+    #[test]
+    fn test_retry_resets_on_success() {
+        let mut queue = ParallelCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule_with(Box::new(FlakyCommand { ticks: 0 }), FailurePolicy::Retry { after: 0, limit: 1 }).unwrap();
+        assert!(queue.consume().unwrap());
+        assert_eq!(queue.attempts()[0].failures, 1);
+        assert!(queue.consume().unwrap());
+        assert_eq!(queue.attempts()[0].failures, 0);
+        assert!(queue.render().unwrap().ends_with("}] }"));
+        for _ in 0..3 {
+            assert!(queue.consume().unwrap());
+        }
+        assert!(!queue.consume().unwrap());
+    }
+
+    #[test]
+    fn test_retry_atomic_once_per_tick() {
+        let world = Arc::new(Mutex::new(World::new()));
+        let context = SnapshotContext::new(world.clone(), Arc::new(Mutex::new(OrderBook::new())), &[], &[], &[]);
+        let mut queue = ParallelCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule_with(Box::new(TestFailCommand::new(5)), FailurePolicy::Retry { after: 0, limit: 10 }).unwrap();
+        queue.schedule(Box::new(TestFailCommand::new(0))).unwrap();
+        assert_eq!(queue.consume_atomic(&context).unwrap().len(), 1);
+        assert_eq!(queue.attempts()[0].failures, 1);
+        assert_eq!(queue.commands().len(), 1);
+        assert_eq!(queue.consume_atomic(&context).unwrap().len(), 1);
+        assert_eq!(queue.attempts()[0].failures, 2);
+    }
+
+    #[test]
+    fn test_skip() {
+        let mut queue = ParallelCommandQueue::with_policy(FailurePolicy::Skip);
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(TestFailCommand::new(1))).unwrap();
+        queue.schedule(Box::new(TestNextCommand::new())).unwrap();
+        assert!(queue.consume().unwrap());
+        assert_eq!(queue.render().unwrap(), "{ \"type\": \"parallel_queue\", \"commands\": [{ \"type\": \"testnext\" }] }");
+    }
+
+    #[test]
+    fn test_skip_failures() {
+        let mut queue = ParallelCommandQueue::with_policy(FailurePolicy::Skip);
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(TestFailCommand::new(1))).unwrap();
+        queue.schedule(Box::new(TestFailCommand::new(2))).unwrap();
+        let failures = queue.consume_at(7).unwrap();
+        assert_eq!(failures.iter().map(|failure| failure.tick).collect::<Vec<u64>>(), vec![7, 7]);
+        queue.schedule(Box::new(TestFailCommand::new(1))).unwrap();
+        assert!(!queue.consume().unwrap());
+        let failures = queue.take_failures();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].tick, 8);
+        assert_eq!(failures[0].error, Error::Other("test command failed"));
+        assert_eq!(queue.take_failures().len(), 0);
+    }
+
+    #[test]
+    fn test_escalate() {
+        let mut queue = ParallelCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(TestFailCommand::new(1))).unwrap();
+        assert_eq!(queue.consume(), Err(Error::Other("test command failed")));
+    }
+
+    #[test]
+    fn test_abort() {
+        let mut inner = ParallelCommandQueue::with_policy(FailurePolicy::Abort);
+        inner.schedule(Box::new(TestFailCommand::new(1))).unwrap();
+        let mut queue = ParallelCommandQueue::with_policy(FailurePolicy::Skip);
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(inner)).unwrap();
+        assert_eq!(queue.consume(), Err(Error::Aborted { error: Box::new(Error::Other("test command failed")) }));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_failing() {
+        let mut queue = ParallelCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule_with(Box::new(TestFailCommand::new(1)), FailurePolicy::Retry { after: 1, limit: 1 }).unwrap();
+        queue.consume().unwrap();
+        assert_eq!(::serde_json::to_value(&queue as &Command).unwrap(),
+            ::serde_json::from_str::<::serde_json::Value>(&queue.render().unwrap()).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut queue = ParallelCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(TestNextCommand::new())).unwrap();
+        queue.schedule(Box::new(TestCommand::new())).unwrap();
+        assert_eq!(::serde_json::to_value(&queue as &Command).unwrap(),
+            ::serde_json::from_str::<::serde_json::Value>(&queue.render().unwrap()).unwrap());
+    }
+
     #[test]
     fn test_render() {
         let mut queue = ParallelCommandQueue::new();
@@ -0,0 +1,346 @@
+use warehouse::command::Command;
+use warehouse::object::{ Bot, Location, Object };
+use warehouse::{ World, SnapshotContext, WAIT_LIMIT, Error, quote };
+#[cfg(feature = "serde")]
+use warehouse::command::command::field;
+
+use std::sync::{ Arc, Mutex };
+
+pub struct BotPathMoveCommand {
+    bot: Arc<Mutex<Bot>>,
+    location: Location,
+    world: Arc<Mutex<World>>,
+    path: Vec<Location>,
+    waited: u32
+}
+
+impl BotPathMoveCommand {
+    pub fn new(bot: Arc<Mutex<Bot>>, location: Location, world: Arc<Mutex<World>>) -> Box<Self> {
+        Box::new(Self {
+            bot: bot,
+            location: location,
+            world: world,
+            path: Vec::new(),
+            waited: 0
+        })
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_checkpoint(value: &::serde_json::Value, context: &SnapshotContext) -> Result<Box<Self>, Error> {
+        Ok(Box::new(Self {
+            bot: context.bot(&field::<String>(value, "bot")?)?,
+            location: field(value, "location")?,
+            world: context.world(),
+            path: field(value, "path")?,
+            waited: match value.get("waited") {
+                Some(_) => field(value, "waited")?,
+                None => 0
+            }
+        }))
+    }
+    fn plan(&mut self) -> Result<(), Error> {
+        let (id, from) = {
+            let bot = self.bot.lock().unwrap();
+            (bot.id().to_owned(), *bot.location())
+        };
+        let mut world = self.world.lock().unwrap();
+        world.release(&id);
+        match world.find_path(&id, from, self.location) {
+            Some(path) => {
+                world.reserve_path(&id, from, &path)?;
+                self.path = path;
+                Ok(())
+            },
+            None => Err(Error::NoPath { from: from, to: self.location })
+        }
+    }
+    fn permanent(&self, err: &Error) -> bool {
+        match *err {
+            Error::NoPath { from, to } => {
+                let id = self.bot.lock().unwrap().id().to_owned();
+                from != to && self.world.lock().unwrap().check_goal(&id, to).is_err()
+            },
+            Error::LocationOccupied { .. } | Error::LocationReserved { .. } | Error::SwapConflict { .. } => false,
+            _ => true
+        }
+    }
+    fn wait(&mut self, err: Error) -> Result<bool, Error> {
+        self.path.clear();
+        self.waited += 1;
+        if self.waited < WAIT_LIMIT {
+            return Ok(true);
+        }
+        let mut bot = self.bot.lock().unwrap();
+        self.world.lock().unwrap().release(bot.id());
+        bot.unlock()?;
+        Err(err)
+    }
+}
+
+impl Command for BotPathMoveCommand {
+    fn initialize(&mut self) -> Result<(), Error> {
+        self.bot.lock().unwrap().lock()?;
+        if let Err(err) = self.plan() {
+            let permanent = self.permanent(&err);
+            let mut bot = self.bot.lock().unwrap();
+            self.world.lock().unwrap().get_metrics().record_failed(bot.id());
+            if permanent {
+                bot.unlock().unwrap();
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+    fn consume(&mut self) -> Result<bool, Error> {
+        let (id, current) = {
+            let bot = self.bot.lock().unwrap();
+            (bot.id().to_owned(), *bot.location())
+        };
+        if current != self.location {
+            let blocked = match self.path.first() {
+                Some(next) => *next != current && self.world.lock().unwrap().check_passage(&id, *next) > 0,
+                None => true
+            };
+            if blocked {
+                self.world.lock().unwrap().get_metrics().record_blocked(&id);
+                if let Err(err) = self.plan() {
+                    return self.wait(err);
+                }
+                self.waited = 0;
+            }
+            let next = self.path.remove(0);
+            if next != current {
+                self.world.lock().unwrap().notify_will_move(self.bot.clone(), next)?;
+                *self.bot.lock().unwrap().get_location() = next;
+            }
+        }
+        if self.path.len() > 0 {
+            return Ok(true);
+        }
+        let mut bot = self.bot.lock().unwrap();
+        bot.unlock()?;
+        self.world.lock().unwrap().release(bot.id());
+        Ok(false)
+    }
+    fn render(&self) -> Result<String, Error> {
+        let bot = self.bot.lock().unwrap();
+        let path = self.path.iter()
+            .map(|location: &Location| location.render())
+            .collect::<Vec<String>>()
+            .join(", ");
+        Ok(format!("{{ \"type\": \"{}\", \"bot\": {}, \"location\": {}, \"path\": [{}] }}",
+            "path_move", quote(bot.id()), self.location.render(), path))
+    }
+    fn abort(&mut self) {
+        let mut bot = self.bot.lock().unwrap();
+        self.world.lock().unwrap().release(bot.id());
+        bot.unlock().ok();
+    }
+    fn objects(&self) -> Option<Vec<String>> {
+        Some(vec![self.bot.lock().unwrap().id().to_owned()])
+    }
+    fn snapshot(&self, context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        Ok(Box::new(Self {
+            bot: context.bot(self.bot.lock().unwrap().id())?,
+            location: self.location,
+            world: context.world(),
+            path: self.path.clone(),
+            waited: self.waited
+        }))
+    }
+    #[cfg(feature = "serde")]
+    fn checkpoint(&self) -> Result<::serde_json::Value, Error> {
+        let mut value = self.to_value()?;
+        value["waited"] = json!(self.waited);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::object::Shelf;
+
+    fn setup() -> (Arc<Mutex<World>>, Arc<Mutex<Bot>>) {
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::new();
+        world.lock().unwrap().add_items(vec![bot.clone()]);
+        (world, bot)
+    }
+
+    fn locked_shelf(world: &Arc<Mutex<World>>, location: Location) {
+        let shelf = Shelf::new();
+        *shelf.lock().unwrap().get_location() = location;
+        shelf.lock().unwrap().lock().unwrap();
+        world.lock().unwrap().add_items(vec![shelf]);
+    }
+
+    #[test]
+    fn test_initialize_lock() {
+        let (world, bot) = setup();
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(3, 0, 0), world);
+        cmd.initialize().unwrap();
+        assert!(*bot.lock().unwrap().get_lock());
+        assert_eq!(cmd.path.len(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected="NoPath")]
+    fn test_initialize_no_path() {
+        let (world, bot) = setup();
+        locked_shelf(&world, Location::new(3, 0, 0));
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(3, 0, 0), world);
+        cmd.initialize().unwrap();
+    }
+
+    #[test]
+    fn test_initialize_no_path_unlock() {
+        let (world, bot) = setup();
+        locked_shelf(&world, Location::new(3, 0, 0));
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(3, 0, 0), world);
+        assert!(cmd.initialize().is_err());
+        assert!(!*bot.lock().unwrap().get_lock());
+    }
+
+    #[test]
+    fn test_consume() {
+        let (world, bot) = setup();
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(2, 1, 0), world.clone());
+        cmd.initialize().unwrap();
+        assert!(cmd.consume().unwrap());
+        assert!(cmd.consume().unwrap());
+        assert!(!cmd.consume().unwrap());
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(2, 1, 0));
+        assert!(!*bot.lock().unwrap().get_lock());
+        assert_eq!(world.lock().unwrap().check_location(Location::new(0, 0, 0)), 0);
+        assert_eq!(world.lock().unwrap().check_location(Location::new(2, 1, 0)), 1);
+    }
+
+    #[test]
+    fn test_consume_around_shelf() {
+        let (world, bot) = setup();
+        locked_shelf(&world, Location::new(1, 0, 0));
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(2, 0, 0), world.clone());
+        cmd.initialize().unwrap();
+        let mut ticks = 1;
+        while cmd.consume().unwrap() {
+            assert_ne!(*bot.lock().unwrap().location(), Location::new(1, 0, 0));
+            ticks += 1;
+        }
+        assert_eq!(ticks, 4);
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(2, 0, 0));
+    }
+
+    #[test]
+    fn test_consume_under_shelf() {
+        let (world, bot) = setup();
+        let shelf = Shelf::new();
+        *shelf.lock().unwrap().get_location() = Location::new(1, 0, 0);
+        world.lock().unwrap().add_items(vec![shelf.clone()]);
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(2, 0, 0), world.clone());
+        cmd.initialize().unwrap();
+        assert_eq!(cmd.path, vec![Location::new(1, 0, 0), Location::new(2, 0, 0)]);
+        world.lock().unwrap().lift(bot.lock().unwrap().id(), Shelf::new()).unwrap();
+        assert_eq!(world.lock().unwrap().check_passage(bot.lock().unwrap().id(), Location::new(1, 0, 0)), 1);
+        world.lock().unwrap().lower(bot.lock().unwrap().id()).unwrap();
+        while cmd.consume().unwrap() {}
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(2, 0, 0));
+        assert_eq!(*shelf.lock().unwrap().location(), Location::new(1, 0, 0));
+    }
+
+    #[test]
+    fn test_consume_replan() {
+        let (world, bot) = setup();
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(2, 0, 0), world.clone());
+        cmd.initialize().unwrap();
+        locked_shelf(&world, Location::new(1, 0, 0));
+        while cmd.consume().unwrap() {}
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(2, 0, 0));
+    }
+
+    #[test]
+    fn test_consume_wait_for_reservation() {
+        let (world, bot) = setup();
+        world.lock().unwrap().reserve_path("bot-other", Location::new(1, 1, 0),
+            &vec![Location::new(1, 0, 0), Location::new(1, 0, 0), Location::new(1, 1, 0)]).unwrap();
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(1, 0, 0), world.clone());
+        cmd.initialize().unwrap();
+        assert_eq!(cmd.path.len(), 3);
+        while cmd.consume().unwrap() {
+            world.lock().unwrap().advance_tick();
+        }
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(1, 0, 0));
+    }
+
+    #[test]
+    fn test_consume_blocked_wait() {
+        let (world, bot) = setup();
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(1, 0, 0), world.clone());
+        cmd.initialize().unwrap();
+        locked_shelf(&world, Location::new(1, 0, 0));
+        assert!(cmd.consume().unwrap());
+        assert!(cmd.consume().unwrap());
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(0, 0, 0));
+        assert!(*bot.lock().unwrap().get_lock());
+        assert_eq!(world.lock().unwrap().metrics().bot(bot.lock().unwrap().id()).unwrap().blocked, 2);
+        for _ in 3..WAIT_LIMIT {
+            assert!(cmd.consume().unwrap());
+        }
+        assert_eq!(cmd.consume(), Err(Error::NoPath { from: Location::new(0, 0, 0), to: Location::new(1, 0, 0) }));
+        assert!(!*bot.lock().unwrap().get_lock());
+    }
+
+    #[test]
+    fn test_initialize_reserved_goal() {
+        let (world, bot) = setup();
+        world.lock().unwrap().reserve_path("bot-other", Location::new(3, 0, 0), &vec![Location::new(2, 0, 0)]).unwrap();
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(2, 0, 0), world.clone());
+        cmd.initialize().unwrap();
+        assert!(cmd.path.is_empty());
+        assert!(cmd.consume().unwrap());
+        world.lock().unwrap().release("bot-other");
+        while cmd.consume().unwrap() {
+            world.lock().unwrap().advance_tick();
+        }
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(2, 0, 0));
+    }
+
+    #[test]
+    fn test_consume_arrived() {
+        let (world, bot) = setup();
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(0, 0, 0), world);
+        cmd.initialize().unwrap();
+        assert!(!cmd.consume().unwrap());
+        assert!(!*bot.lock().unwrap().get_lock());
+    }
+
+    #[test]
+    fn test_consume_elevator() {
+        let (world, bot) = setup();
+        world.lock().unwrap().add_elevator(1, 0, 2);
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(0, 0, 1), world.clone());
+        cmd.initialize().unwrap();
+        assert_eq!(cmd.path, vec![Location::new(1, 0, 0), Location::new(1, 0, 0), Location::new(1, 0, 1), Location::new(0, 0, 1)]);
+        while cmd.consume().unwrap() {
+            world.lock().unwrap().advance_tick();
+        }
+        assert_eq!(*bot.lock().unwrap().location(), Location::new(0, 0, 1));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let (world, bot) = setup();
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(2, 0, 0), world);
+        cmd.initialize().unwrap();
+        assert_eq!(cmd.to_value().unwrap(), ::serde_json::from_str::<::serde_json::Value>(&cmd.render().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_render() {
+        let (world, bot) = setup();
+        let mut cmd = BotPathMoveCommand::new(bot.clone(), Location::new(1, 0, 0), world);
+        cmd.initialize().unwrap();
+        assert_eq!(cmd.render().unwrap(),
+            format!("{{ \"type\": \"path_move\", \"bot\": \"{}\", \"location\": {{ \"x\": 1, \"y\": 0, \"z\": 0 }}, \"path\": [{{ \"x\": 1, \"y\": 0, \"z\": 0 }}] }}", bot.lock().unwrap().id()));
+    }
+}
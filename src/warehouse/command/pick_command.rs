@@ -1,13 +1,16 @@
 use warehouse::command::Command;
 use warehouse::object::{ Site, Object };
-use warehouse::ItemSet;
+use warehouse::{ ItemSet, OrderBook, SnapshotContext, Error, quote };
+#[cfg(feature = "serde")]
+use warehouse::command::command::field;
 
 use std::sync::{ Arc, Mutex };
 
 pub struct PickCommand {
     site: Arc<Mutex<Site>>,
     item: ItemSet,
-    reverse: bool
+    reverse: bool,
+    order: Option<(Arc<Mutex<OrderBook>>, String)>
 }
 
 pub struct SitePutCommand {
@@ -21,9 +24,48 @@ impl SitePickCommand {
         Box::new(PickCommand {
             site: site,
             item: item,
-            reverse: false
+            reverse: false,
+            order: None
         })
     }
+    pub fn for_order(site: Arc<Mutex<Site>>, item: ItemSet, orders: Arc<Mutex<OrderBook>>, order: &str) -> Box<PickCommand> {
+        Box::new(PickCommand {
+            site: site,
+            item: item,
+            reverse: false,
+            order: Some((orders, order.to_owned()))
+        })
+    }
+}
+
+impl PickCommand {
+    fn check(&self, site: &Site, orders: Option<&OrderBook>) -> Result<(), Error> {
+        let (from, to) = match self.reverse {
+            true => (&site.pick_storage, site.storage()),
+            false => (site.storage(), &site.pick_storage)
+        };
+        let have = from.have(self.item.0);
+        if have < self.item.1 {
+            return Err(Error::NotEnoughItem { item: self.item.0, have: have, want: self.item.1 });
+        }
+        to.check_add(self.item.0, self.item.1)?;
+        match (orders, self.order.as_ref()) {
+            (Some(orders), Some(&(_, ref id))) => orders.check_fulfill(id, self.item),
+            _ => Ok(())
+        }
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_checkpoint(value: &::serde_json::Value, context: &SnapshotContext) -> Result<Box<Self>, Error> {
+        Ok(Box::new(Self {
+            site: context.site(&field::<String>(value, "site")?)?,
+            item: (field(value, "item")?, field(value, "count")?),
+            reverse: field::<String>(value, "type")? == "put",
+            order: match value.get("order") {
+                Some(_) => Some((context.orders(), field(value, "order")?)),
+                None => None
+            }
+        }))
+    }
 }
 
 impl SitePutCommand {
@@ -31,65 +73,81 @@ impl SitePutCommand {
         Box::new(PickCommand {
             site: site,
             item: item,
-            reverse: true
+            reverse: true,
+            order: None
         })
     }
 }
 
 impl Command for PickCommand {
-    fn initialize(&mut self) -> Result<(), &'static str> {
+    fn initialize(&mut self) -> Result<(), Error> {
         let mut site = self.site.lock().unwrap();
         {
-            let storage = site.storage();
-            if self.reverse {
-                if site.pick_storage.have(self.item.0) < self.item.1 {
-                    return Err("not enough item");
-                }
-            } else {
-                if storage.have(self.item.0) < self.item.1 {
-                    return Err("not enough item");
-                }
-            }
+            let orders = self.order.as_ref().map(|&(ref orders, _)| orders.lock().unwrap());
+            self.check(&site, orders.as_ref().map(|orders| &**orders))?;
         }
         site.lock()
     }
-    fn consume(&mut self) -> Result<bool, &'static str> {
+    fn consume(&mut self) -> Result<bool, Error> {
         let mut site = self.site.lock().unwrap();
-        {
-            let storage = site.get_storage();
-            match self.reverse {
-                false => storage.take(self.item.0, self.item.1).unwrap(),
-                true => storage.add(self.item.0, self.item.1).unwrap()
+        let mut orders = self.order.as_ref().map(|&(ref orders, _)| orders.lock().unwrap());
+        self.check(&site, orders.as_ref().map(|orders| &**orders))?;
+        match self.reverse {
+            false => {
+                site.get_storage().take(self.item.0, self.item.1)?;
+                site.pick_storage.add(self.item.0, self.item.1)?;
+            },
+            true => {
+                site.pick_storage.take(self.item.0, self.item.1)?;
+                site.get_storage().add(self.item.0, self.item.1)?;
             }
         }
-        {
-            match self.reverse {
-                true => site.pick_storage.take(self.item.0, self.item.1).unwrap(),
-                false => site.pick_storage.add(self.item.0, self.item.1).unwrap()
-            }
-            
+        if let (Some(ref mut orders), Some(&(_, ref id))) = (orders.as_mut(), self.order.as_ref()) {
+            orders.fulfill(id, self.item)?;
         }
         site.unlock()?;
         Ok(false)
     }
-    fn render(&self) -> Result<String, &'static str> {
+    fn render(&self) -> Result<String, Error> {
         let site = self.site.lock().unwrap();
         match self.reverse {
-            true => Ok(format!("{{ \"type\": \"{}\", \"site\": \"{}\", \"item\": {}, \"count\": {} }}", 
-            "put", site.id(), self.item.0, self.item.1)),
-            false => Ok(format!("{{ \"type\": \"{}\", \"site\": \"{}\", \"item\": {}, \"count\": {} }}", 
-            "pick", site.id(), self.item.0, self.item.1))
+            true => Ok(format!("{{ \"type\": \"{}\", \"site\": {}, \"item\": {}, \"count\": {} }}", 
+            "put", quote(site.id()), self.item.0, self.item.1)),
+            false => match self.order {
+                Some((_, ref id)) => Ok(format!("{{ \"type\": \"{}\", \"site\": {}, \"item\": {}, \"count\": {}, \"order\": {} }}", 
+                "pick", quote(site.id()), self.item.0, self.item.1, quote(id))),
+                None => Ok(format!("{{ \"type\": \"{}\", \"site\": {}, \"item\": {}, \"count\": {} }}", 
+                "pick", quote(site.id()), self.item.0, self.item.1))
+            }
         }
 
     }
+    fn abort(&mut self) {
+        self.site.lock().unwrap().unlock().ok();
+    }
+    fn orders(&self) -> Vec<String> {
+        self.order.iter().map(|&(_, ref id)| id.clone()).collect()
+    }
+    fn objects(&self) -> Option<Vec<String>> {
+        Some(vec![self.site.lock().unwrap().id().to_owned()])
+    }
+    fn snapshot(&self, context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        Ok(Box::new(Self {
+            site: context.site(self.site.lock().unwrap().id())?,
+            item: self.item,
+            reverse: self.reverse,
+            order: self.order.as_ref().map(|&(_, ref id)| (context.orders(), id.clone()))
+        }))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use warehouse::Capacity;
 
     #[test]
-    #[should_panic(expected="not enough item")]
+    #[should_panic(expected="NotEnoughItem")]
     fn test_pick_initialize() {
         let site = Site::new();
         {
@@ -133,6 +191,61 @@ mod tests {
         assert_eq!((*site.lock().unwrap()).pick_storage.have(1), 2);
         assert!(!*site.lock().unwrap().get_lock());
     }
+    #[test]
+    #[should_panic(expected="OverCapacity")]
+    fn test_pick_over_capacity() {
+        let site = Site::new();
+        {
+            let mut site = site.lock().unwrap();
+            site.get_storage().add(1, 3).unwrap();
+            site.pick_storage.set_capacity(Capacity { units: Some(2), ..Capacity::unlimited() });
+        }
+        let mut cmd = SitePickCommand::new(site, (1, 3));
+        cmd.initialize().unwrap();
+    }
+
+    #[test]
+    fn test_pick_for_order() {
+        let site = Site::new();
+        site.lock().unwrap().get_storage().add(1, 3).unwrap();
+        let orders = Arc::new(Mutex::new(OrderBook::new()));
+        let id = orders.lock().unwrap().add(vec![(1, 2)], 0, 10).unwrap();
+        orders.lock().unwrap().assign(&id, site.lock().unwrap().id()).unwrap();
+        let mut cmd = SitePickCommand::for_order(site.clone(), (1, 2), orders.clone(), &id);
+        cmd.initialize().unwrap();
+        cmd.consume().unwrap();
+        assert_eq!(orders.lock().unwrap().completed(), 1);
+        assert!(!*site.lock().unwrap().get_lock());
+        assert_eq!(cmd.render().unwrap(), format!("{{ \"type\": \"pick\", \"site\": \"{}\", \"item\": 1, \"count\": 2, \"order\": \"{}\" }}", site.lock().unwrap().id(), id));
+    }
+
+    #[test]
+    #[should_panic(expected="NotEnoughItem")]
+    fn test_pick_for_order_line() {
+        let site = Site::new();
+        site.lock().unwrap().get_storage().add(1, 3).unwrap();
+        let orders = Arc::new(Mutex::new(OrderBook::new()));
+        let id = orders.lock().unwrap().add(vec![(1, 2)], 0, 10).unwrap();
+        orders.lock().unwrap().assign(&id, site.lock().unwrap().id()).unwrap();
+        let mut cmd = SitePickCommand::for_order(site.clone(), (1, 3), orders.clone(), &id);
+        cmd.initialize().unwrap();
+    }
+
+    #[test]
+    fn test_pick_for_order_atomic() {
+        let site = Site::new();
+        site.lock().unwrap().get_storage().add(1, 3).unwrap();
+        let orders = Arc::new(Mutex::new(OrderBook::new()));
+        let id = orders.lock().unwrap().add(vec![(1, 2)], 0, 10).unwrap();
+        orders.lock().unwrap().assign(&id, site.lock().unwrap().id()).unwrap();
+        let mut cmd = SitePickCommand::for_order(site.clone(), (1, 2), orders.clone(), &id);
+        cmd.initialize().unwrap();
+        orders.lock().unwrap().fulfill(&id, (1, 1)).unwrap();
+        assert!(cmd.consume().is_err());
+        assert_eq!(site.lock().unwrap().storage().have(1), 3);
+        assert_eq!(site.lock().unwrap().pick_storage.have(1), 0);
+    }
+
     #[test]
     fn test_pick_render() {
         let site = Site::new();
@@ -141,7 +254,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected="not enough item")]
+    #[should_panic(expected="NotEnoughItem")]
     fn test_put_initialize() {
         let site = Site::new();
         {
@@ -181,6 +294,17 @@ mod tests {
         assert_eq!((*site.lock().unwrap()).storage().have(1), 2);
         assert!(!*site.lock().unwrap().get_lock());
     }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let site = Site::new();
+        let cmd = SitePickCommand::new(site.clone(), (2, 233));
+        assert_eq!(cmd.to_value().unwrap(), ::serde_json::from_str::<::serde_json::Value>(&cmd.render().unwrap()).unwrap());
+        let cmd = SitePickCommand::for_order(site.clone(), (2, 233), Arc::new(Mutex::new(OrderBook::new())), "ord-0001");
+        assert_eq!(cmd.to_value().unwrap(), ::serde_json::from_str::<::serde_json::Value>(&cmd.render().unwrap()).unwrap());
+        let cmd = SitePutCommand::new(site.clone(), (2, 233));
+        assert_eq!(cmd.to_value().unwrap(), ::serde_json::from_str::<::serde_json::Value>(&cmd.render().unwrap()).unwrap());
+    }
     #[test]
     fn test_put_render() {
         let site = Site::new();
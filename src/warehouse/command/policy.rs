@@ -0,0 +1,110 @@
+use warehouse::{ Error, quote };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum FailurePolicy {
+    Retry { after: u64, limit: u32 },
+    Skip,
+    Escalate,
+    Abort
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Retry,
+    Skip,
+    Fail(Error)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Attempts {
+    pub policy: FailurePolicy,
+    pub failures: u32,
+    pub wait: u64,
+    pub error: Option<String>
+}
+
+impl Attempts {
+    pub fn new(policy: FailurePolicy) -> Self {
+        Self {
+            policy: policy,
+            failures: 0,
+            wait: 0,
+            error: None
+        }
+    }
+    pub fn waiting(&self) -> bool {
+        self.wait > 0
+    }
+    pub fn advance_tick(&mut self) {
+        if self.wait > 0 {
+            self.wait -= 1;
+        }
+    }
+    pub fn succeed(&mut self) {
+        self.failures = 0;
+        self.error = None;
+    }
+    pub fn fail(&mut self, err: Error) -> Outcome {
+        self.failures += 1;
+        self.error = Some(err.to_string());
+        if let Error::Aborted { .. } = err {
+            return Outcome::Fail(err);
+        }
+        match self.policy {
+            FailurePolicy::Retry { after, limit } if self.failures <= limit => {
+                self.wait = after;
+                Outcome::Retry
+            },
+            FailurePolicy::Retry { .. } | FailurePolicy::Skip => Outcome::Skip,
+            FailurePolicy::Escalate => Outcome::Fail(err),
+            FailurePolicy::Abort => Outcome::Fail(Error::Aborted { error: Box::new(err) })
+        }
+    }
+    pub fn render(&self, index: usize) -> String {
+        format!("{{ \"index\": {}, \"failures\": {}, \"error\": {} }}", index, self.failures, quote(self.error.clone().unwrap_or_default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error() -> Error {
+        Error::AlreadyLocked { id: "bot-0001".to_owned() }
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut attempts = Attempts::new(FailurePolicy::Retry { after: 2, limit: 2 });
+        assert_eq!(attempts.fail(error()), Outcome::Retry);
+        assert!(attempts.waiting());
+        attempts.advance_tick();
+        attempts.advance_tick();
+        assert!(!attempts.waiting());
+        assert_eq!(attempts.fail(error()), Outcome::Retry);
+        assert_eq!(attempts.fail(error()), Outcome::Skip);
+        assert_eq!(attempts.render(1), "{ \"index\": 1, \"failures\": 3, \"error\": \"object bot-0001 already locked\" }");
+    }
+
+    #[test]
+    fn test_retry_consecutive() {
+        let mut attempts = Attempts::new(FailurePolicy::Retry { after: 0, limit: 1 });
+        assert_eq!(attempts.fail(error()), Outcome::Retry);
+        attempts.succeed();
+        assert_eq!(attempts.failures, 0);
+        assert_eq!(attempts.fail(error()), Outcome::Retry);
+        assert_eq!(attempts.fail(error()), Outcome::Skip);
+    }
+
+    #[test]
+    fn test_escalate() {
+        assert_eq!(Attempts::new(FailurePolicy::Skip).fail(error()), Outcome::Skip);
+        assert_eq!(Attempts::new(FailurePolicy::Escalate).fail(error()), Outcome::Fail(error()));
+        let aborted = Error::Aborted { error: Box::new(error()) };
+        assert_eq!(Attempts::new(FailurePolicy::Abort).fail(error()), Outcome::Fail(aborted.clone()));
+        assert_eq!(Attempts::new(FailurePolicy::Skip).fail(aborted.clone()), Outcome::Fail(aborted));
+    }
+}
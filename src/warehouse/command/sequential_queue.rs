@@ -1,2 +1,260 @@
+use warehouse::command::{ Command, CommandQueue };
+use warehouse::command::command::snapshot_commands;
+#[cfg(feature = "serde")]
+use warehouse::command::command::{ field, checkpoint_commands, restore_commands };
+use warehouse::{ SnapshotContext, Error };
+
 pub struct SequentialCommandQueue {
+    queue: Vec<Box<Command>>,
+    initialized: bool,
+    head_initialized: bool
+}
+
+impl SequentialCommandQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: Vec::new(),
+            initialized: false,
+            head_initialized: false
+        }
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_checkpoint(value: &::serde_json::Value, context: &SnapshotContext) -> Result<Box<Self>, Error> {
+        Ok(Box::new(Self {
+            queue: restore_commands(value, context)?,
+            initialized: field(value, "initialized")?,
+            head_initialized: field(value, "head_initialized")?
+        }))
+    }
+    fn initialize_head(&mut self) -> Result<(), Error> {
+        if !self.head_initialized && self.queue.len() > 0 {
+            self.queue[0].initialize()?;
+            self.head_initialized = true;
+        }
+        Ok(())
+    }
+}
+
+impl CommandQueue for SequentialCommandQueue {
+    fn schedule(&mut self, command: Box<Command>) -> Result<(), Error> {
+        self.queue.push(command);
+        if self.initialized {
+            if let Err(err) = self.initialize_head() {
+                self.queue.pop();
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+    fn commands(&self) -> &Vec<Box<Command>> {
+        &self.queue
+    }
+}
+
+impl Command for SequentialCommandQueue {
+    fn initialize(&mut self) -> Result<(), Error> {
+        self.initialized = true;
+        self.initialize_head()
+    }
+
+    fn consume(&mut self) -> Result<bool, Error> {
+        self.initialize_head()?;
+        if self.queue.len() == 0 {
+            return Ok(false);
+        }
+        if self.queue[0].consume()? {
+            return Ok(true);
+        }
+        self.queue.remove(0);
+        self.head_initialized = false;
+        self.initialize_head()?;
+        Ok(self.queue.len() > 0)
+    }
+    fn render(&self) -> Result<String, Error> {
+        let mut error_flag: Option<Error> = None;
+        let result = self.queue.iter()
+            .map(|command: &Box<Command>| match command.render() {
+                Ok(result) => result,
+                Err(err) => {
+                    error_flag = Some(err);
+                    "".to_owned()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        match error_flag {
+            Some(err) => Err(err),
+            None => Ok(format!("{{ \"type\": \"sequential_queue\", \"commands\": [{}] }}", result))
+        }
+    }
+    fn abort(&mut self) {
+        if self.head_initialized {
+            self.queue[0].abort();
+        }
+    }
+    fn orders(&self) -> Vec<String> {
+        self.queue.iter().flat_map(|command| command.orders()).collect()
+    }
+    fn objects(&self) -> Option<Vec<String>> {
+        self.queue.iter().map(|command| command.objects()).collect::<Option<Vec<Vec<String>>>>().map(|ids| ids.concat())
+    }
+    fn snapshot(&self, context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        Ok(Box::new(Self {
+            queue: snapshot_commands(&self.queue, context)?,
+            initialized: self.initialized,
+            head_initialized: self.head_initialized
+        }))
+    }
+    #[cfg(feature = "serde")]
+    fn checkpoint(&self) -> Result<::serde_json::Value, Error> {
+        Ok(json!({
+            "type": "sequential_queue",
+            "commands": checkpoint_commands(&self.queue)?,
+            "initialized": self.initialized,
+            "head_initialized": self.head_initialized
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::command::TestCommand;
+    use warehouse::command::PanicCommand;
+    use warehouse::command::ConsumePanicCommand;
+    use warehouse::command::TestNextCommand;
+    use warehouse::command::ParallelCommandQueue;
+
+    #[test]
+    fn test_new() {
+        let mut queue = SequentialCommandQueue::new();
+        queue.initialize().unwrap();
+        assert!(!queue.consume().unwrap());
+    }
+
+    #[test]
+    fn test_schedule_initialize_head_only() {
+        let mut queue = SequentialCommandQueue::new();
+        let (cmd1, cmd2) = (TestCommand::new(), TestCommand::new());
+        let (dat1, dat2) = (cmd1.data.clone(), cmd2.data.clone());
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(cmd1)).unwrap();
+        queue.schedule(Box::new(cmd2)).unwrap();
+        assert!(dat1.lock().unwrap().initialized);
+        assert!(!dat2.lock().unwrap().initialized);
+    }
+
+    #[test]
+    fn test_schedule_before_initialize() {
+        let mut queue = SequentialCommandQueue::new();
+        let cmd = TestCommand::new();
+        let dat = cmd.data.clone();
+        queue.schedule(Box::new(cmd)).unwrap();
+        assert!(!dat.lock().unwrap().initialized);
+        queue.initialize().unwrap();
+        assert!(dat.lock().unwrap().initialized);
+    }
+
+    #[test]
+    #[should_panic(expected="panic command initialized")]
+    fn test_schedule_panic() {
+        let mut queue = SequentialCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(PanicCommand::new())).unwrap();
+    }
+
+    #[test]
+    fn test_schedule_panic_removed() {
+        let mut queue = SequentialCommandQueue::new();
+        queue.initialize().unwrap();
+        assert!(queue.schedule(Box::new(PanicCommand::new())).is_err());
+        assert_eq!(queue.queue.len(), 0);
+    }
+
+    #[test]
+    fn test_consume_in_order() {
+        let mut queue = SequentialCommandQueue::new();
+        let (cmd1, cmd2) = (TestCommand::new(), TestCommand::new());
+        let (dat1, dat2) = (cmd1.data.clone(), cmd2.data.clone());
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(cmd1)).unwrap();
+        queue.schedule(Box::new(cmd2)).unwrap();
+        assert!(queue.consume().unwrap());
+        assert!(dat1.lock().unwrap().consumed);
+        assert!(dat2.lock().unwrap().initialized);
+        assert!(!dat2.lock().unwrap().consumed);
+        assert!(!queue.consume().unwrap());
+        assert!(dat2.lock().unwrap().consumed);
+        assert_eq!(queue.queue.len(), 0);
+    }
+
+    #[test]
+    fn test_wait_for_next() {
+        let mut queue = SequentialCommandQueue::new();
+        let cmd = TestCommand::new();
+        let dat = cmd.data.clone();
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(TestNextCommand::new())).unwrap();
+        queue.schedule(Box::new(cmd)).unwrap();
+        assert!(queue.consume().unwrap());
+        assert!(queue.consume().unwrap());
+        assert!(!dat.lock().unwrap().initialized);
+        assert_eq!(queue.queue.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected="panic command initialized")]
+    fn test_consume_initialize_panic() {
+        let mut queue = SequentialCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(TestCommand::new())).unwrap();
+        queue.schedule(Box::new(PanicCommand::new())).unwrap();
+        queue.consume().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected="consume panic command consumed")]
+    fn test_consume_panic() {
+        let mut queue = SequentialCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(ConsumePanicCommand::new())).unwrap();
+        queue.consume().unwrap();
+    }
+
+    #[test]
+    fn test_in_parallel_queue() {
+        let mut parallel = ParallelCommandQueue::new();
+        let mut queue = SequentialCommandQueue::new();
+        let (cmd1, cmd2) = (TestCommand::new(), TestCommand::new());
+        let (dat1, dat2) = (cmd1.data.clone(), cmd2.data.clone());
+        queue.schedule(Box::new(cmd1)).unwrap();
+        queue.schedule(Box::new(cmd2)).unwrap();
+        parallel.initialize().unwrap();
+        parallel.schedule(Box::new(queue)).unwrap();
+        assert!(dat1.lock().unwrap().initialized);
+        assert!(parallel.consume().unwrap());
+        assert!(!parallel.consume().unwrap());
+        assert!(dat2.lock().unwrap().consumed);
+        assert_eq!(parallel.commands().len(), 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut queue = SequentialCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(TestNextCommand::new())).unwrap();
+        queue.schedule(Box::new(TestCommand::new())).unwrap();
+        assert_eq!(::serde_json::to_value(&queue as &Command).unwrap(),
+            ::serde_json::from_str::<::serde_json::Value>(&queue.render().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut queue = SequentialCommandQueue::new();
+        queue.initialize().unwrap();
+        queue.schedule(Box::new(TestNextCommand::new())).unwrap();
+        queue.schedule(Box::new(TestCommand::new())).unwrap();
+        assert_eq!(queue.render().unwrap(), "{ \"type\": \"sequential_queue\", \"commands\": [{ \"type\": \"testnext\" }, { \"type\": \"test\" }] }");
+    }
 }
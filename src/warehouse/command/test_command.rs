@@ -1,4 +1,5 @@
 use super::command::Command;
+use warehouse::{ SnapshotContext, Error };
 use std::sync::{ Arc, Mutex };
 
 pub struct TestCommandStatus {
@@ -22,15 +23,15 @@ impl TestCommand {
 }
 
 impl Command for TestCommand {
-    fn initialize(&mut self) -> Result<(), &'static str> {
+    fn initialize(&mut self) -> Result<(), Error> {
         (*self.data.lock().unwrap()).initialized = true;
         Ok(())
     }
-    fn consume(&mut self) -> Result<bool, &'static str> {
+    fn consume(&mut self) -> Result<bool, Error> {
         (*self.data.lock().unwrap()).consumed = true;
         Ok(false)
     }
-    fn render(&self) -> Result<String, &'static str> {
+    fn render(&self) -> Result<String, Error> {
         Ok("{ \"type\": \"test\" }".to_owned())
     }
 }
@@ -45,17 +46,48 @@ impl TestNextCommand {
 }
 
 impl Command for TestNextCommand {
-    fn initialize(&mut self) -> Result<(), &'static str> {
+    fn initialize(&mut self) -> Result<(), Error> {
         Ok(())
     }
-    fn consume(&mut self) -> Result<bool, &'static str> {
+    fn consume(&mut self) -> Result<bool, Error> {
         Ok(true)
     }
-    fn render(&self) -> Result<String, &'static str> {
+    fn render(&self) -> Result<String, Error> {
         Ok("{ \"type\": \"testnext\" }".to_owned())
     }
 }
 
+pub struct TestFailCommand {
+    pub failures: u32
+}
+
+impl TestFailCommand {
+    pub fn new(failures: u32) -> Self {
+        Self {
+            failures: failures
+        }
+    }
+}
+
+impl Command for TestFailCommand {
+    fn initialize(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn consume(&mut self) -> Result<bool, Error> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(Error::Other("test command failed"));
+        }
+        Ok(false)
+    }
+    fn render(&self) -> Result<String, Error> {
+        Ok("{ \"type\": \"testfail\" }".to_owned())
+    }
+    fn snapshot(&self, _context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        Ok(Box::new(Self::new(self.failures)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,6 +117,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_fail_consume() {
+        let mut cmd = TestFailCommand::new(1);
+        assert!(cmd.consume().is_err());
+        assert!(!cmd.consume().unwrap());
+    }
+
     #[test]
     fn test_next_consume() {
         let mut cmd = TestNextCommand::new();
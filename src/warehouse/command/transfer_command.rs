@@ -1,11 +1,15 @@
 use warehouse::command::Command;
 use warehouse::object::{ Object, Bot };
+use warehouse::{ ItemSet, SnapshotContext, Error, quote };
+#[cfg(feature = "serde")]
+use warehouse::command::command::field;
 use std::sync::{ Arc, Mutex };
-use std::mem::swap;
 
 pub struct TransferCommand {
     from: Arc<Mutex<Object>>,
-    to: Arc<Mutex<Object>>
+    to: Arc<Mutex<Object>>,
+    items: Option<Vec<ItemSet>>,
+    moved: Option<Vec<ItemSet>>
 }
 
 pub struct BotTransferToCommand {
@@ -15,7 +19,12 @@ pub struct BotTransferToCommand {
 impl BotTransferToCommand {
     pub fn new(from: Arc<Mutex<Bot>>, to: Arc<Mutex<Object>>) -> Box<TransferCommand> {
         Box::new(TransferCommand {
-            from: from, to: to
+            from: from, to: to, items: None, moved: None
+        })
+    }
+    pub fn with_items(from: Arc<Mutex<Bot>>, to: Arc<Mutex<Object>>, items: Vec<ItemSet>) -> Box<TransferCommand> {
+        Box::new(TransferCommand {
+            from: from, to: to, items: Some(items), moved: None
         })
     }
 }
@@ -26,20 +35,59 @@ pub struct BotTransferFromCommand {
 impl BotTransferFromCommand {
     pub fn new(from: Arc<Mutex<Object>>, to: Arc<Mutex<Bot>>) -> Box<TransferCommand> {
         Box::new(TransferCommand {
-            from: from, to: to
+            from: from, to: to, items: None, moved: None
         })
     }
+    pub fn with_items(from: Arc<Mutex<Object>>, to: Arc<Mutex<Bot>>, items: Vec<ItemSet>) -> Box<TransferCommand> {
+        Box::new(TransferCommand {
+            from: from, to: to, items: Some(items), moved: None
+        })
+    }
+}
+
+impl TransferCommand {
+    #[cfg(feature = "serde")]
+    pub fn from_checkpoint(value: &::serde_json::Value, context: &SnapshotContext) -> Result<Box<Self>, Error> {
+        let items = match value.get("items").and_then(::serde_json::Value::as_array) {
+            Some(items) => Some(items.iter()
+                .map(|set| Ok((field(set, "item")?, field(set, "count")?)))
+                .collect::<Result<Vec<ItemSet>, Error>>()?),
+            None => None
+        };
+        Ok(Box::new(Self {
+            from: context.object(&field::<String>(value, "from")?)?,
+            to: context.object(&field::<String>(value, "to")?)?,
+            items: items,
+            moved: None
+        }))
+    }
+    fn check(&self, from: &Object, to: &Object) -> Result<(), Error> {
+        match self.items {
+            Some(ref items) => {
+                for &(item, _) in items {
+                    let have = from.storage().have(item);
+                    let want = items.iter().filter(|set| set.0 == item).map(|set| set.1).sum();
+                    if have < want {
+                        return Err(Error::NotEnoughItem { item: item, have: have, want: want });
+                    }
+                }
+                to.storage().check_add_all(items)
+            },
+            None => to.storage().check_add_all(&from.storage().items)
+        }
+    }
 }
 
 impl Command for TransferCommand {
-    fn initialize(&mut self) -> Result<(), &'static str> {
+    fn initialize(&mut self) -> Result<(), Error> {
+        if Arc::ptr_eq(&self.from, &self.to) {
+            return Err(Error::SelfTransfer { id: self.from.lock().unwrap().id().to_owned() });
+        }
         let mut from = self.from.lock().unwrap();
         let mut to = self.to.lock().unwrap();
-        if to.storage().items.len() > 0 {
-            return Err("target storage not empty");
-        }
+        self.check(&*from, &*to)?;
         if !to.location().nearby(*from.location()) {
-            return Err("source and target are far away");
+            return Err(Error::FarAway { from: *from.location(), to: *to.location() });
         }
         from.lock()?;
         match to.lock() {
@@ -51,22 +99,61 @@ impl Command for TransferCommand {
         };
         Ok(())
     }
-    fn consume(&mut self) -> Result<bool, &'static str> {
+    fn consume(&mut self) -> Result<bool, Error> {
         let mut from = self.from.lock().unwrap();
         let mut to = self.to.lock().unwrap();
-        {
-            let from_storage = from.get_storage();
-            let to_storage = to.get_storage();
-            swap(&mut from_storage.items, &mut to_storage.items);
+        self.check(&*from, &*to)?;
+        let moved = match self.items {
+            Some(ref items) => items.clone(),
+            None => from.storage().items.clone()
+        };
+        for &(item, count) in &moved {
+            from.get_storage().take(item, count)?;
+            to.get_storage().add(item, count)?;
         }
-        from.unlock().unwrap();
-        to.unlock().unwrap();
+        if self.items.is_none() {
+            self.moved = Some(moved);
+        }
+        from.unlock()?;
+        to.unlock()?;
         Ok(false)
     }
-    fn render(&self) -> Result<String, &'static str> {
+    fn render(&self) -> Result<String, Error> {
         let from = self.from.lock().unwrap();
         let to = self.to.lock().unwrap();
-        Ok(format!("{{ \"type\": \"{}\", \"from\": \"{}\", \"to\": \"{}\" }}", "transfer", from.id(), to.id()))
+        match self.items {
+            Some(ref items) => {
+                let items = items.iter()
+                    .map(|set: &ItemSet| format!("{{ \"item\": {}, \"count\": {} }}", set.0, set.1))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Ok(format!("{{ \"type\": \"{}\", \"from\": {}, \"to\": {}, \"items\": [{}] }}", "transfer", quote(from.id()), quote(to.id()), items))
+            },
+            None => {
+                let moved = self.moved.as_ref().unwrap_or(&from.storage().items).iter()
+                    .map(|set: &ItemSet| format!("{{ \"item\": {}, \"count\": {} }}", set.0, set.1))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Ok(format!("{{ \"type\": \"{}\", \"from\": {}, \"to\": {}, \"moved\": [{}] }}", "transfer", quote(from.id()), quote(to.id()), moved))
+            }
+        }
+    }
+    fn abort(&mut self) {
+        self.from.lock().unwrap().unlock().ok();
+        self.to.lock().unwrap().unlock().ok();
+    }
+    fn objects(&self) -> Option<Vec<String>> {
+        Some(vec![self.from.lock().unwrap().id().to_owned(), self.to.lock().unwrap().id().to_owned()])
+    }
+    fn snapshot(&self, context: &SnapshotContext) -> Result<Box<Command>, Error> {
+        let from = self.from.lock().unwrap().id().to_owned();
+        let to = self.to.lock().unwrap().id().to_owned();
+        Ok(Box::new(Self {
+            from: context.object(&from)?,
+            to: context.object(&to)?,
+            items: self.items.clone(),
+            moved: self.moved.clone()
+        }))
     }
 }
 
@@ -74,6 +161,7 @@ impl Command for TransferCommand {
 mod tests {
     use super::*;
     use warehouse::object::TestObject;
+    use warehouse::Capacity;
 
     #[test]
     fn test_lock() {
@@ -128,22 +216,47 @@ mod tests {
         }
     }
     #[test]
-    #[should_panic(expected="target storage not empty")]
-    fn test_storage_full() {
+    fn test_storage_not_empty() {
         let bot = Bot::new();
         let obj = TestObject::new();
         {
             let (mut bot, mut obj) = (bot.lock().unwrap(), obj.lock().unwrap());
             let (bot_storage, obj_storage) = (bot.get_storage(), obj.get_storage());
             bot_storage.add(1, 1).unwrap(); bot_storage.add(3, 1).unwrap();
-            obj_storage.add(2, 1).unwrap();
+            obj_storage.add(1, 2).unwrap();
         }
         let mut cmd = BotTransferToCommand::new(bot.clone(), obj.clone());
         cmd.initialize().unwrap();
         cmd.consume().unwrap();
+        assert_eq!(bot.lock().unwrap().storage().items, vec![]);
+        assert_eq!(obj.lock().unwrap().storage().items, vec![(1, 3), (3, 1)]);
     }
     #[test]
-    #[should_panic(expected="source and target are far away")]
+    fn test_consume_revalidates() {
+        let bot = Bot::new();
+        let obj = TestObject::new();
+        bot.lock().unwrap().get_storage().add(1, 2).unwrap();
+        let mut cmd = BotTransferToCommand::with_items(bot.clone(), obj.clone(), vec![(1, 2)]);
+        cmd.initialize().unwrap();
+        bot.lock().unwrap().get_storage().take(1, 1).unwrap();
+        assert_eq!(cmd.consume().unwrap_err(), Error::NotEnoughItem { item: 1, have: 1, want: 2 });
+        assert_eq!(bot.lock().unwrap().storage().items, vec![(1, 1)]);
+        assert_eq!(obj.lock().unwrap().storage().items, vec![]);
+        obj.lock().unwrap().get_storage().set_capacity(Capacity { units: Some(0), ..Capacity::unlimited() });
+        let mut cmd = BotTransferToCommand::new(bot.clone(), obj.clone());
+        assert_eq!(cmd.consume().unwrap_err(), Error::OverCapacity { item: 1, overflow: 1 });
+        assert_eq!(bot.lock().unwrap().storage().items, vec![(1, 1)]);
+    }
+    #[test]
+    fn test_self_transfer() {
+        let bot = Bot::new();
+        let id = bot.lock().unwrap().id().to_owned();
+        let mut cmd = BotTransferToCommand::new(bot.clone(), bot.clone());
+        assert_eq!(cmd.initialize().unwrap_err(), Error::SelfTransfer { id: id });
+        assert!(!*bot.lock().unwrap().get_lock());
+    }
+    #[test]
+    #[should_panic(expected="FarAway")]
     fn test_far_away() {
         let bot = Bot::new();
         let obj = TestObject::new();
@@ -158,12 +271,89 @@ mod tests {
         cmd.consume().unwrap();
     }
     #[test]
-    fn test_render() {
+    fn test_partial() {
         let bot = Bot::new();
         let obj = TestObject::new();
+        {
+            let (mut bot, mut obj) = (bot.lock().unwrap(), obj.lock().unwrap());
+            obj.get_storage().add(1, 5).unwrap(); obj.get_storage().add(2, 1).unwrap();
+            bot.get_storage().add(1, 1).unwrap();
+        }
+        let mut cmd = BotTransferFromCommand::with_items(obj.clone(), bot.clone(), vec![(1, 3)]);
+        cmd.initialize().unwrap();
+        assert!(!cmd.consume().unwrap());
+        assert_eq!(bot.lock().unwrap().storage().items, vec![(1, 4)]);
+        assert_eq!(obj.lock().unwrap().storage().items, vec![(1, 2), (2, 1)]);
+        assert!(!*bot.lock().unwrap().get_lock());
+        assert!(!*obj.lock().unwrap().get_lock());
+    }
+    #[test]
+    #[should_panic(expected="have: 5, want: 6")]
+    fn test_partial_not_enough() {
+        let bot = Bot::new();
+        let obj = TestObject::new();
+        bot.lock().unwrap().get_storage().add(1, 5).unwrap();
+        let mut cmd = BotTransferToCommand::with_items(bot.clone(), obj.clone(), vec![(1, 3), (1, 3)]);
+        cmd.initialize().unwrap();
+    }
+    #[test]
+    #[should_panic(expected="OverCapacity")]
+    fn test_over_capacity() {
+        let bot = Bot::new();
+        let obj = TestObject::new();
+        bot.lock().unwrap().get_storage().add(1, 5).unwrap();
+        obj.lock().unwrap().get_storage().set_capacity(Capacity { units: Some(4), ..Capacity::unlimited() });
         let mut cmd = BotTransferToCommand::new(bot.clone(), obj.clone());
         cmd.initialize().unwrap();
+    }
+    #[test]
+    fn test_partial_over_capacity() {
+        let bot = Bot::new();
+        let obj = TestObject::new();
+        bot.lock().unwrap().get_storage().add(1, 5).unwrap();
+        obj.lock().unwrap().get_storage().set_capacity(Capacity { skus: Some(1), ..Capacity::unlimited() });
+        obj.lock().unwrap().get_storage().add(2, 1).unwrap();
+        let mut cmd = BotTransferToCommand::with_items(bot.clone(), obj.clone(), vec![(1, 1)]);
+        assert_eq!(cmd.initialize().unwrap_err(), Error::OverCapacity { item: 1, overflow: 1 });
+        assert!(!*bot.lock().unwrap().get_lock());
+    }
+    #[test]
+    fn test_partial_not_enough_unlocked() {
+        let bot = Bot::new();
+        let obj = TestObject::new();
+        let mut cmd = BotTransferToCommand::with_items(bot.clone(), obj.clone(), vec![(1, 1)]);
+        assert!(cmd.initialize().is_err());
+        assert!(!*bot.lock().unwrap().get_lock());
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let bot = Bot::new();
+        let obj = TestObject::new();
+        let cmd = BotTransferToCommand::new(bot.clone(), obj.clone());
+        assert_eq!(cmd.to_value().unwrap(), ::serde_json::from_str::<::serde_json::Value>(&cmd.render().unwrap()).unwrap());
+        let cmd = BotTransferToCommand::with_items(bot.clone(), obj.clone(), vec![(1, 2), (3, 1)]);
+        assert_eq!(cmd.to_value().unwrap(), ::serde_json::from_str::<::serde_json::Value>(&cmd.render().unwrap()).unwrap());
+    }
+    #[test]
+    fn test_render_partial() {
+        let bot = Bot::new();
+        let obj = TestObject::new();
+        let cmd = BotTransferToCommand::with_items(bot.clone(), obj.clone(), vec![(1, 2)]);
         assert_eq!(cmd.render().unwrap(),
-            format!("{{ \"type\": \"transfer\", \"from\": \"{}\", \"to\": \"{}\" }}", bot.lock().unwrap().id(), obj.lock().unwrap().id()));
+            format!("{{ \"type\": \"transfer\", \"from\": \"{}\", \"to\": \"{}\", \"items\": [{{ \"item\": 1, \"count\": 2 }}] }}", bot.lock().unwrap().id(), obj.lock().unwrap().id()));
+    }
+    #[test]
+    fn test_render() {
+        let bot = Bot::new();
+        let obj = TestObject::new();
+        bot.lock().unwrap().get_storage().add(1, 2).unwrap();
+        let mut cmd = BotTransferToCommand::new(bot.clone(), obj.clone());
+        cmd.initialize().unwrap();
+        let expected = format!("{{ \"type\": \"transfer\", \"from\": \"{}\", \"to\": \"{}\", \"moved\": [{{ \"item\": 1, \"count\": 2 }}] }}",
+            bot.lock().unwrap().id(), obj.lock().unwrap().id());
+        assert_eq!(cmd.render().unwrap(), expected);
+        cmd.consume().unwrap();
+        assert_eq!(cmd.render().unwrap(), expected);
     }
 }
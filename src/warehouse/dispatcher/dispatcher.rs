@@ -0,0 +1,44 @@
+use warehouse::command::Command;
+use warehouse::object::{ Bot, Shelf, Site, Object };
+use warehouse::{ World, OrderBook, Error };
+use std::sync::{ Arc, Mutex };
+
+pub struct DispatchContext<'a> {
+    pub world: &'a Arc<Mutex<World>>,
+    pub bots: &'a Vec<Arc<Mutex<Bot>>>,
+    pub shelves: &'a Vec<Arc<Mutex<Shelf>>>,
+    pub sites: &'a Vec<Arc<Mutex<Site>>>,
+    pub orders: &'a Arc<Mutex<OrderBook>>
+}
+
+impl<'a> DispatchContext<'a> {
+    pub fn idle_bots(&self) -> Vec<Arc<Mutex<Bot>>> {
+        self.bots.iter()
+            .filter(|bot| {
+                let mut bot = bot.lock().unwrap();
+                !*bot.get_lock() && bot.storage().items.len() == 0
+            })
+            .cloned()
+            .collect()
+    }
+    pub fn free_sites(&self) -> Vec<Arc<Mutex<Site>>> {
+        let orders = self.orders.lock().unwrap();
+        self.sites.iter()
+            .filter(|site| {
+                let mut site = site.lock().unwrap();
+                !*site.get_lock() && orders.active(site.id()).is_none()
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+pub struct Job {
+    pub order: String,
+    pub site: String,
+    pub command: Box<Command>
+}
+
+pub trait Dispatcher {
+    fn dispatch(&mut self, context: &DispatchContext) -> Result<Vec<Job>, Error>;
+}
@@ -0,0 +1,236 @@
+use warehouse::dispatcher::{ Dispatcher, DispatchContext, Job };
+use warehouse::command::{ Command, CommandQueue, SequentialCommandQueue, BotPathMoveCommand,
+    BotTransferFromCommand, BotTransferToCommand, SitePickCommand };
+use warehouse::object::{ Object, Location };
+use warehouse::{ ItemSet, OrderStatus, Error };
+use std::collections::HashMap;
+
+pub struct GreedyDispatcher {
+    claimed: HashMap<String, String>
+}
+
+impl GreedyDispatcher {
+    pub fn new() -> Self {
+        Self {
+            claimed: HashMap::new()
+        }
+    }
+    fn stands(free: &Vec<Location>, target: Location, current: Location) -> Vec<Location> {
+        let mut stands = free.clone();
+        if current.nearby(target) {
+            stands.push(current);
+        }
+        stands
+    }
+}
+
+impl Dispatcher for GreedyDispatcher {
+    fn dispatch(&mut self, context: &DispatchContext) -> Result<Vec<Job>, Error> {
+        let pending = {
+            let orders = context.orders.lock().unwrap();
+            self.claimed.retain(|_, order| orders.get(order).map_or(false, |order| order.status == OrderStatus::Assigned));
+            orders.pending().iter()
+                .map(|order| (order.id.clone(), order.lines.clone()))
+                .collect::<Vec<(String, Vec<ItemSet>)>>()
+        };
+        let mut bots = context.idle_bots();
+        let mut sites = context.free_sites();
+        let mut distances: HashMap<String, HashMap<Location, u32>> = HashMap::new();
+        let mut jobs = Vec::new();
+        for (order, lines) in pending {
+            if bots.len() == 0 || sites.len() == 0 {
+                break;
+            }
+            let world = context.world.lock().unwrap();
+            let shelves = context.shelves.iter().enumerate()
+                .filter(|&(_, shelf)| {
+                    let mut shelf = shelf.lock().unwrap();
+                    !*shelf.get_lock() && !self.claimed.contains_key(shelf.id())
+                        && lines.iter().all(|line| shelf.storage().have(line.0) >= line.1)
+                })
+                .map(|(i, shelf)| {
+                    let location = *shelf.lock().unwrap().location();
+                    (i, location, world.free_neighbours(location))
+                })
+                .collect::<Vec<(usize, Location, Vec<Location>)>>();
+            if shelves.len() == 0 {
+                continue;
+            }
+            let mut best: Option<(u32, usize, usize, Location)> = None;
+            for (j, bot) in bots.iter().enumerate() {
+                let bot = bot.lock().unwrap();
+                if bot.storage().check_add_all(&lines).is_err() {
+                    continue;
+                }
+                let current = *bot.location();
+                let reachable = distances.entry(bot.id().to_owned()).or_insert_with(|| world.travel_distances(current));
+                for &(i, location, ref free) in &shelves {
+                    for stand in GreedyDispatcher::stands(free, location, current) {
+                        let distance = match reachable.get(&stand) {
+                            Some(&distance) => distance,
+                            None => continue
+                        };
+                        if best.map_or(true, |best| distance < best.0) {
+                            best = Some((distance, i, j, stand));
+                        }
+                    }
+                }
+            }
+            let (_, i, j, shelf_stand) = match best {
+                Some(best) => best,
+                None => continue
+            };
+            let reachable = world.travel_distances(shelf_stand);
+            let mut site_best: Option<(u32, usize, Location)> = None;
+            for (k, site) in sites.iter().enumerate() {
+                let site = site.lock().unwrap();
+                if site.storage().check_add_all(&lines).is_err() {
+                    continue;
+                }
+                let location = *site.location();
+                for stand in GreedyDispatcher::stands(&world.free_neighbours(location), location, shelf_stand) {
+                    let distance = match reachable.get(&stand) {
+                        Some(&distance) => distance,
+                        None => continue
+                    };
+                    if site_best.map_or(true, |best| distance < best.0) {
+                        site_best = Some((distance, k, stand));
+                    }
+                }
+            }
+            let (_, k, site_stand) = match site_best {
+                Some(best) => best,
+                None => continue
+            };
+            let (bot, shelf, site) = (bots.remove(j), context.shelves[i].clone(), sites.remove(k));
+            let shelf_id = shelf.lock().unwrap().id().to_owned();
+            let site_id = site.lock().unwrap().id().to_owned();
+            let mut queue = SequentialCommandQueue::new();
+            queue.schedule(BotPathMoveCommand::new(bot.clone(), shelf_stand, context.world.clone()))?;
+            queue.schedule(BotTransferFromCommand::with_items(shelf.clone(), bot.clone(), lines.clone()))?;
+            queue.schedule(BotPathMoveCommand::new(bot.clone(), site_stand, context.world.clone()))?;
+            queue.schedule(BotTransferToCommand::with_items(bot.clone(), site.clone(), lines.clone()))?;
+            for line in lines {
+                queue.schedule(SitePickCommand::for_order(site.clone(), line, context.orders.clone(), &order))?;
+            }
+            self.claimed.insert(shelf_id, order.clone());
+            jobs.push(Job {
+                order: order,
+                site: site_id,
+                command: Box::new(queue) as Box<Command>
+            });
+        }
+        Ok(jobs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::object::{ Bot, Shelf, Site };
+    use warehouse::{ World, OrderBook, Capacity };
+    use std::sync::{ Arc, Mutex };
+
+    fn place<T: Object>(obj: Arc<Mutex<T>>, location: Location) -> Arc<Mutex<T>> {
+        *obj.lock().unwrap().get_location() = location;
+        obj
+    }
+
+    fn setup() -> (Arc<Mutex<World>>, Vec<Arc<Mutex<Bot>>>, Vec<Arc<Mutex<Shelf>>>, Vec<Arc<Mutex<Site>>>, Arc<Mutex<OrderBook>>) {
+        let bots = vec![place(Bot::new(), Location::new(0, 0, 0)), place(Bot::new(), Location::new(4, 0, 0))];
+        let shelves = vec![place(Shelf::new(), Location::new(1, 2, 0)), place(Shelf::new(), Location::new(4, 2, 0))];
+        let sites = vec![place(Site::new(), Location::new(6, 4, 0))];
+        shelves[0].lock().unwrap().get_storage().add(1, 3).unwrap();
+        shelves[1].lock().unwrap().get_storage().add(1, 3).unwrap();
+        let mut world = World::new();
+        world.add_items(bots.iter().cloned().map(|obj| obj as Arc<Mutex<Object>>).collect());
+        world.add_items(shelves.iter().cloned().map(|obj| obj as Arc<Mutex<Object>>).collect());
+        world.add_items(sites.iter().cloned().map(|obj| obj as Arc<Mutex<Object>>).collect());
+        (Arc::new(Mutex::new(world)), bots, shelves, sites, Arc::new(Mutex::new(OrderBook::new())))
+    }
+
+    #[test]
+    fn test_dispatch_nearest_bot() {
+        let (world, bots, shelves, sites, orders) = setup();
+        let order = orders.lock().unwrap().add(vec![(1, 2)], 0, 100).unwrap();
+        let context = DispatchContext { world: &world, bots: &bots, shelves: &shelves, sites: &sites, orders: &orders };
+        let jobs = GreedyDispatcher::new().dispatch(&context).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].order, order);
+        assert_eq!(jobs[0].site, sites[0].lock().unwrap().id());
+        let rendered = jobs[0].command.render().unwrap();
+        assert!(rendered.contains(&format!("\"bot\": \"{}\"", bots[1].lock().unwrap().id())));
+        assert!(rendered.contains(&format!("\"from\": \"{}\"", shelves[1].lock().unwrap().id())));
+    }
+
+    #[test]
+    fn test_dispatch_walls() {
+        let (world, bots, shelves, sites, orders) = setup();
+        {
+            let mut world = world.lock().unwrap();
+            let grid = world.get_grid();
+            grid.add_wall(Location::new(3, 0, 0));
+            grid.add_wall(Location::new(5, 0, 0));
+            grid.add_wall(Location::new(4, 1, 0));
+        }
+        orders.lock().unwrap().add(vec![(1, 2)], 0, 100).unwrap();
+        let context = DispatchContext { world: &world, bots: &bots, shelves: &shelves, sites: &sites, orders: &orders };
+        let jobs = GreedyDispatcher::new().dispatch(&context).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert!(jobs[0].command.render().unwrap().contains(&format!("\"bot\": \"{}\"", bots[0].lock().unwrap().id())));
+    }
+
+    #[test]
+    fn test_dispatch_no_stock() {
+        let (world, bots, shelves, sites, orders) = setup();
+        orders.lock().unwrap().add(vec![(1, 4)], 0, 100).unwrap();
+        orders.lock().unwrap().add(vec![(2, 1)], 0, 100).unwrap();
+        let context = DispatchContext { world: &world, bots: &bots, shelves: &shelves, sites: &sites, orders: &orders };
+        assert_eq!(GreedyDispatcher::new().dispatch(&context).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_dispatch_claimed() {
+        let (world, bots, shelves, mut sites, orders) = setup();
+        sites.push(place(Site::new(), Location::new(0, 4, 0)));
+        let first = orders.lock().unwrap().add(vec![(1, 1)], 0, 100).unwrap();
+        let context = DispatchContext { world: &world, bots: &bots, shelves: &shelves, sites: &sites, orders: &orders };
+        let mut dispatcher = GreedyDispatcher::new();
+        let jobs = dispatcher.dispatch(&context).unwrap();
+        orders.lock().unwrap().assign(&first, &jobs[0].site).unwrap();
+        orders.lock().unwrap().add(vec![(1, 1)], 0, 100).unwrap();
+        let jobs = dispatcher.dispatch(&context).unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert!(jobs[0].command.render().unwrap().contains(&format!("\"from\": \"{}\"", shelves[0].lock().unwrap().id())));
+    }
+
+    #[test]
+    fn test_dispatch_capacity() {
+        let (world, bots, shelves, sites, orders) = setup();
+        bots[1].lock().unwrap().get_storage().set_capacity(Capacity { units: Some(1), ..Capacity::unlimited() });
+        orders.lock().unwrap().add(vec![(1, 2)], 0, 100).unwrap();
+        let context = DispatchContext { world: &world, bots: &bots, shelves: &shelves, sites: &sites, orders: &orders };
+        let jobs = GreedyDispatcher::new().dispatch(&context).unwrap();
+        assert!(jobs[0].command.render().unwrap().contains(&format!("\"bot\": \"{}\"", bots[0].lock().unwrap().id())));
+        sites[0].lock().unwrap().get_storage().set_capacity(Capacity { units: Some(1), ..Capacity::unlimited() });
+        assert_eq!(GreedyDispatcher::new().dispatch(&context).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_dispatch_run() {
+        let (world, bots, shelves, sites, orders) = setup();
+        let order = orders.lock().unwrap().add(vec![(1, 2)], 0, 100).unwrap();
+        let context = DispatchContext { world: &world, bots: &bots, shelves: &shelves, sites: &sites, orders: &orders };
+        let mut job = GreedyDispatcher::new().dispatch(&context).unwrap().remove(0);
+        orders.lock().unwrap().assign(&job.order, &job.site).unwrap();
+        job.command.initialize().unwrap();
+        while job.command.consume().unwrap() {
+            world.lock().unwrap().advance_tick();
+        }
+        assert_eq!(orders.lock().unwrap().get(&order).unwrap().status, OrderStatus::Complete);
+        assert_eq!(sites[0].lock().unwrap().pick_storage.have(1), 2);
+        assert_eq!(sites[0].lock().unwrap().storage().have(1), 0);
+        assert_eq!(shelves[1].lock().unwrap().storage().have(1), 1);
+        assert_eq!(bots[1].lock().unwrap().storage().items.len(), 0);
+    }
+}
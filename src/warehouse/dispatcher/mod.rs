@@ -0,0 +1,5 @@
+mod dispatcher;
+mod greedy;
+
+pub use self::dispatcher::{ Dispatcher, DispatchContext, Job };
+pub use self::greedy::GreedyDispatcher;
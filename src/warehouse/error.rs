@@ -0,0 +1,114 @@
+use warehouse::{ Item, OrderStatus };
+use warehouse::object::Location;
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    LocationOccupied { location: Location, by: String },
+    LocationReserved { location: Location, tick: u64, by: String },
+    SwapConflict { location: Location, by: String },
+    NoPath { from: Location, to: Location },
+    NotEnoughItem { item: Item, have: u32, want: u32 },
+    OverCapacity { item: Item, overflow: u32 },
+    StorageNotEmpty { id: String },
+    SelfTransfer { id: String },
+    AlreadyLocked { id: String },
+    AlreadyUnlocked { id: String },
+    FarAway { from: Location, to: Location },
+    NotInWorld { id: String },
+    UnknownOrder { id: String },
+    InvalidOrderStatus { id: String, status: OrderStatus },
+    InvalidOrderLine { item: Item, count: u32 },
+    AlreadyCarrying { id: String, shelf: String },
+    NotCarrying { id: String },
+    NoElevator { location: Location },
+    OutOfBounds { location: Location },
+    Wall { location: Location },
+    WrongWay { from: Location, to: Location },
+    NoStop { location: Location },
+    Aborted { error: Box<Error> },
+    Checkpoint(String),
+    Other(&'static str)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::LocationOccupied { ref location, ref by } =>
+                write!(f, "location {} occupied by {}", location, by),
+            Error::LocationReserved { ref location, tick, ref by } =>
+                write!(f, "location {} reserved by {} at tick {}", location, by, tick),
+            Error::SwapConflict { ref location, ref by } =>
+                write!(f, "swap conflict with {} at {}", by, location),
+            Error::NoPath { ref from, ref to } =>
+                write!(f, "no path from {} to {}", from, to),
+            Error::NotEnoughItem { item, have, want } =>
+                write!(f, "not enough item {}: have {}, want {}", item, have, want),
+            Error::OverCapacity { item, overflow } =>
+                write!(f, "no room for item {}: over capacity by {}", item, overflow),
+            Error::StorageNotEmpty { ref id } =>
+                write!(f, "storage of {} not empty", id),
+            Error::SelfTransfer { ref id } =>
+                write!(f, "cannot transfer from {} to itself", id),
+            Error::AlreadyLocked { ref id } =>
+                write!(f, "object {} already locked", id),
+            Error::AlreadyUnlocked { ref id } =>
+                write!(f, "object {} already unlocked", id),
+            Error::FarAway { ref from, ref to } =>
+                write!(f, "{} and {} are far away", from, to),
+            Error::NotInWorld { ref id } =>
+                write!(f, "object {} not in world", id),
+            Error::UnknownOrder { ref id } =>
+                write!(f, "unknown order {}", id),
+            Error::InvalidOrderStatus { ref id, status } =>
+                write!(f, "order {} is {}", id, status),
+            Error::InvalidOrderLine { item, count } =>
+                write!(f, "invalid order line for item {}: count {}", item, count),
+            Error::AlreadyCarrying { ref id, ref shelf } =>
+                write!(f, "{} already carries {}", id, shelf),
+            Error::NotCarrying { ref id } =>
+                write!(f, "{} carries no shelf", id),
+            Error::NoElevator { ref location } =>
+                write!(f, "no elevator at {}", location),
+            Error::OutOfBounds { ref location } =>
+                write!(f, "location {} out of bounds", location),
+            Error::Wall { ref location } =>
+                write!(f, "wall at {}", location),
+            Error::WrongWay { ref from, ref to } =>
+                write!(f, "move from {} to {} runs against the lane", from, to),
+            Error::NoStop { ref location } =>
+                write!(f, "cannot stop at {}", location),
+            Error::Aborted { ref error } =>
+                write!(f, "run aborted: {}", error),
+            Error::Checkpoint(ref message) =>
+                write!(f, "invalid checkpoint: {}", message),
+            Error::Other(message) =>
+                write!(f, "{}", message)
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let err = Error::NotEnoughItem { item: 1, have: 3, want: 4 };
+        assert_eq!(format!("{}", err), "not enough item 1: have 3, want 4");
+        let err = Error::FarAway { from: Location::new(0, 0, 0), to: Location::new(2, 0, 0) };
+        assert_eq!(format!("{}", err), "(0, 0, 0) and (2, 0, 0) are far away");
+    }
+
+    #[test]
+    fn test_match() {
+        let err = Error::AlreadyLocked { id: "bot-1".to_owned() };
+        match err {
+            Error::AlreadyLocked { ref id } => assert_eq!(id, "bot-1"),
+            _ => panic!("wrong error kind")
+        }
+    }
+}
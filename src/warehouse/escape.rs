@@ -0,0 +1,29 @@
+use std::fmt::{ Display, Write };
+
+pub fn quote<T: Display>(value: T) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.to_string().chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => { write!(quoted, "\\u{:04x}", c as u32).unwrap(); },
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("bot"), "\"bot\"");
+        assert_eq!(quote("a \"b\"\\c\n\u{1}"), "\"a \\\"b\\\"\\\\c\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,187 @@
+use warehouse::object::Location;
+use warehouse::{ Item, Error, quote };
+use std::io::{ self, Write };
+use std::sync::{ Arc, Mutex };
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    CommandInitialized { command: String },
+    CommandCompleted { command: String },
+    CommandFailed { command: String, error: Error },
+    ObjectMoved { id: String, from: Location, to: Location },
+    StorageChanged { id: String, item: Item, before: u32, after: u32, pick_storage: bool },
+    ObjectLocked { id: String },
+    ObjectUnlocked { id: String }
+}
+
+impl Event {
+    fn fields(&self) -> String {
+        match *self {
+            Event::CommandInitialized { ref command } =>
+                format!("\"event\": \"command_initialized\", \"command\": {}", command),
+            Event::CommandCompleted { ref command } =>
+                format!("\"event\": \"command_completed\", \"command\": {}", command),
+            Event::CommandFailed { ref command, ref error } =>
+                format!("\"event\": \"command_failed\", \"command\": {}, \"error\": {}", command, quote(error)),
+            Event::ObjectMoved { ref id, from, to } =>
+                format!("\"event\": \"object_moved\", \"id\": {}, \"from\": {}, \"to\": {}", quote(id), from.render(), to.render()),
+            Event::StorageChanged { ref id, item, before, after, pick_storage } =>
+                format!("\"event\": \"storage_changed\", \"id\": {}, \"item\": {}, \"before\": {}, \"after\": {}, \"pick_storage\": {}",
+                    quote(id), item, before, after, pick_storage),
+            Event::ObjectLocked { ref id } =>
+                format!("\"event\": \"object_locked\", \"id\": {}", quote(id)),
+            Event::ObjectUnlocked { ref id } =>
+                format!("\"event\": \"object_unlocked\", \"id\": {}", quote(id))
+        }
+    }
+    pub fn render(&self) -> String {
+        format!("{{ {} }}", self.fields())
+    }
+}
+
+pub trait Observer {
+    fn notify(&mut self, tick: u64, event: &Event);
+}
+
+pub struct EventLog {
+    pub events: Vec<(u64, Event)>
+}
+
+impl EventLog {
+    pub fn new() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            events: Vec::new()
+        }))
+    }
+}
+
+impl Observer for EventLog {
+    fn notify(&mut self, tick: u64, event: &Event) {
+        self.events.push((tick, event.clone()));
+    }
+}
+
+pub struct EventWriter<W: Write> {
+    writer: W,
+    error: Option<io::Error>
+}
+
+impl<W: Write> EventWriter<W> {
+    pub fn new(writer: W) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            writer: writer,
+            error: None
+        }))
+    }
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => self.writer.flush()
+        }
+    }
+}
+
+impl<W: Write> Observer for EventWriter<W> {
+    fn notify(&mut self, tick: u64, event: &Event) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(err) = write!(self.writer, "{{ \"tick\": {}, {} }}\n", tick, event.fields()) {
+            self.error = Some(err);
+        }
+    }
+}
+
+pub struct EventBus {
+    observers: Vec<Arc<Mutex<Observer>>>,
+    pending: Vec<Event>
+}
+
+#[derive(Clone)]
+pub struct EventSender {
+    id: String,
+    pick_storage: bool,
+    bus: Arc<Mutex<EventBus>>
+}
+
+impl EventSender {
+    pub fn new(id: &str, bus: Arc<Mutex<EventBus>>) -> Self {
+        Self {
+            id: id.to_owned(),
+            pick_storage: false,
+            bus: bus
+        }
+    }
+    pub fn for_pick_storage(&self) -> Self {
+        Self {
+            pick_storage: true,
+            ..self.clone()
+        }
+    }
+    pub fn emit(&self, event: Event) {
+        self.bus.lock().unwrap().emit(event);
+    }
+    pub fn storage_changed(&self, item: Item, before: u32, after: u32) {
+        self.emit(Event::StorageChanged { id: self.id.clone(), item: item, before: before, after: after, pick_storage: self.pick_storage });
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            observers: Vec::new(),
+            pending: Vec::new()
+        }))
+    }
+    pub fn subscribe(&mut self, observer: Arc<Mutex<Observer>>) {
+        self.observers.push(observer);
+    }
+    pub fn observed(&self) -> bool {
+        self.observers.len() > 0
+    }
+    pub fn emit(&mut self, event: Event) {
+        if self.observed() {
+            self.pending.push(event);
+        }
+    }
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+    pub fn discard(&mut self, from: usize) {
+        self.pending.truncate(from);
+    }
+    pub fn flush(&mut self, tick: u64) {
+        for event in self.pending.drain(..) {
+            for observer in &self.observers {
+                observer.lock().unwrap().notify(tick, &event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flush() {
+        let log = EventLog::new();
+        let bus = EventBus::new();
+        let mut bus = bus.lock().unwrap();
+        bus.emit(Event::ObjectLocked { id: "bot-0001".to_owned() });
+        bus.subscribe(log.clone());
+        bus.emit(Event::ObjectUnlocked { id: "bot-0001".to_owned() });
+        bus.flush(3);
+        bus.flush(4);
+        assert_eq!(log.lock().unwrap().events, vec![(3, Event::ObjectUnlocked { id: "bot-0001".to_owned() })]);
+    }
+
+    #[test]
+    fn test_writer() {
+        let writer = EventWriter::new(Vec::new());
+        writer.lock().unwrap().notify(2, &Event::ObjectLocked { id: "bot-0001".to_owned() });
+        writer.lock().unwrap().finish().unwrap();
+        assert_eq!(String::from_utf8(writer.lock().unwrap().writer.clone()).unwrap(),
+            "{ \"tick\": 2, \"event\": \"object_locked\", \"id\": \"bot-0001\" }\n");
+    }
+}
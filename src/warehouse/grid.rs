@@ -0,0 +1,128 @@
+use warehouse::object::{ Location, Direction };
+use warehouse::Error;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Cell {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wall: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lane: Option<Direction>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ticks: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub no_stop: bool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridMap {
+    size: Option<Location>,
+    cells: HashMap<Location, Cell>
+}
+
+impl GridMap {
+    pub fn new() -> Self {
+        Self {
+            size: None,
+            cells: HashMap::new()
+        }
+    }
+    pub fn with_size(size: Location) -> Self {
+        Self {
+            size: Some(size),
+            ..Self::new()
+        }
+    }
+    pub fn size(&self) -> Option<Location> {
+        self.size
+    }
+    pub fn contains(&self, location: Location) -> bool {
+        match self.size {
+            Some(size) => location.x < size.x && location.y < size.y && location.z < size.z,
+            None => true
+        }
+    }
+    pub fn cell(&self, location: Location) -> Cell {
+        self.cells.get(&location).cloned().unwrap_or_default()
+    }
+    pub fn get_cell(&mut self, location: Location) -> &mut Cell {
+        self.cells.entry(location).or_insert_with(Cell::default)
+    }
+    pub fn cells(&self) -> Vec<(Location, Cell)> {
+        let mut cells = self.cells.iter()
+            .map(|(&location, &cell)| (location, cell))
+            .collect::<Vec<(Location, Cell)>>();
+        cells.sort_by_key(|&(location, _)| (location.z, location.y, location.x));
+        cells
+    }
+    pub fn add_wall(&mut self, location: Location) {
+        self.get_cell(location).wall = true;
+    }
+    pub fn set_lane(&mut self, location: Location, lane: Direction) {
+        self.get_cell(location).lane = Some(lane);
+    }
+    pub fn set_zone(&mut self, location: Location, ticks: u64) {
+        self.get_cell(location).ticks = ticks;
+    }
+    pub fn add_no_stop(&mut self, location: Location) {
+        self.get_cell(location).no_stop = true;
+    }
+    pub fn can_stop(&self, location: Location) -> bool {
+        !self.cell(location).no_stop
+    }
+    pub fn check(&self, location: Location) -> Result<(), Error> {
+        if !self.contains(location) {
+            return Err(Error::OutOfBounds { location: location });
+        }
+        if self.cell(location).wall {
+            return Err(Error::Wall { location: location });
+        }
+        Ok(())
+    }
+    pub fn check_step(&self, from: Location, to: Location) -> Result<u64, Error> {
+        if from == to {
+            return match self.can_stop(from) {
+                true => Ok(1),
+                false => Err(Error::NoStop { location: from })
+            };
+        }
+        self.check(to)?;
+        if let Some(direction) = from.direction_to(to) {
+            let against = |cell: Cell| cell.lane == Some(direction.opposite());
+            if against(self.cell(from)) || against(self.cell(to)) {
+                return Err(Error::WrongWay { from: from, to: to });
+            }
+        }
+        Ok(self.cell(to).ticks.max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let mut grid = GridMap::with_size(Location::new(3, 3, 1));
+        grid.add_wall(Location::new(1, 1, 0));
+        assert!(grid.check(Location::new(2, 2, 0)).is_ok());
+        assert_eq!(grid.check(Location::new(3, 0, 0)), Err(Error::OutOfBounds { location: Location::new(3, 0, 0) }));
+        assert_eq!(grid.check(Location::new(1, 1, 0)), Err(Error::Wall { location: Location::new(1, 1, 0) }));
+        assert!(GridMap::new().check(Location::new(100, 0, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_check_step() {
+        let mut grid = GridMap::new();
+        let (a, b, c) = (Location::new(0, 0, 0), Location::new(1, 0, 0), Location::new(1, 1, 0));
+        grid.set_lane(b, Direction::East);
+        grid.set_zone(c, 3);
+        grid.add_no_stop(b);
+        assert_eq!(grid.check_step(a, b), Ok(1));
+        assert_eq!(grid.check_step(b, a), Err(Error::WrongWay { from: b, to: a }));
+        assert_eq!(grid.check_step(b, c), Ok(3));
+        assert_eq!(grid.check_step(b, b), Err(Error::NoStop { location: b }));
+        assert_eq!(grid.check_step(a, a), Ok(1));
+    }
+}
@@ -0,0 +1,96 @@
+extern crate uuid;
+
+use std::collections::HashMap;
+use self::uuid::Uuid;
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IdGenerator {
+    Random,
+    Sequential(HashMap<String, u64>),
+    Seeded(u64)
+}
+
+impl IdGenerator {
+    pub fn random() -> Self {
+        IdGenerator::Random
+    }
+    pub fn sequential() -> Self {
+        IdGenerator::Sequential(HashMap::new())
+    }
+    pub fn seeded(seed: u64) -> Self {
+        IdGenerator::Seeded(seed)
+    }
+    pub fn next(&mut self, prefix: &str) -> String {
+        match *self {
+            IdGenerator::Random => format!("{}-{}", prefix, Uuid::new_v4()),
+            IdGenerator::Sequential(ref mut counters) => {
+                let counter = counters.entry(prefix.to_owned()).or_insert(0);
+                *counter += 1;
+                format!("{}-{:04}", prefix, counter)
+            },
+            IdGenerator::Seeded(ref mut state) => {
+                let (high, low) = (splitmix64(state), splitmix64(state));
+                format!("{}-{:016x}{:016x}", prefix, high, low)
+            }
+        }
+    }
+    pub fn observe(&mut self, id: &str) {
+        if let IdGenerator::Sequential(ref mut counters) = *self {
+            if let Some(pos) = id.rfind('-') {
+                if let Ok(number) = id[pos + 1..].parse::<u64>() {
+                    let counter = counters.entry(id[..pos].to_owned()).or_insert(0);
+                    *counter = (*counter).max(number);
+                }
+            }
+        }
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequential() {
+        let mut ids = IdGenerator::sequential();
+        assert_eq!(ids.next("bot"), "bot-0001");
+        assert_eq!(ids.next("bot"), "bot-0002");
+        assert_eq!(ids.next("slf"), "slf-0001");
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut ids = IdGenerator::sequential();
+        ids.observe("bot-0041");
+        ids.observe("ste-e5d1b16a");
+        assert_eq!(ids.next("bot"), "bot-0042");
+        assert_eq!(ids.next("ste"), "ste-0001");
+    }
+
+    #[test]
+    fn test_seeded() {
+        let (mut ids1, mut ids2) = (IdGenerator::seeded(42), IdGenerator::seeded(42));
+        let id = ids1.next("bot");
+        assert_eq!(id, ids2.next("bot"));
+        assert_eq!(id.len(), 36);
+        assert_ne!(id, ids1.next("bot"));
+        assert_ne!(id, IdGenerator::seeded(43).next("bot"));
+    }
+
+    #[test]
+    fn test_random() {
+        let mut ids = IdGenerator::random();
+        let id = ids.next("slf");
+        assert_eq!(&id[..4], "slf-");
+        Uuid::parse_str(&id[4..]).unwrap();
+    }
+}
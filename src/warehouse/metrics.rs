@@ -0,0 +1,204 @@
+use warehouse::object::{ Bot, Site, Object, Location };
+use warehouse::quote;
+use std::collections::{ BTreeMap, HashMap };
+use std::sync::{ Arc, Mutex };
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BotStats {
+    pub distance: u64,
+    pub busy: u64,
+    pub idle: u64,
+    pub blocked: u64,
+    pub failed: u64,
+    pub transfers: u64
+}
+
+impl BotStats {
+    fn add(&mut self, other: &BotStats) {
+        self.distance += other.distance;
+        self.busy += other.busy;
+        self.idle += other.idle;
+        self.blocked += other.blocked;
+        self.failed += other.failed;
+        self.transfers += other.transfers;
+    }
+    pub fn render(&self) -> String {
+        format!("{{ \"distance\": {}, \"busy\": {}, \"idle\": {}, \"blocked\": {}, \"failed\": {}, \"transfers\": {} }}",
+            self.distance, self.busy, self.idle, self.blocked, self.failed, self.transfers)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SiteStats {
+    pub picked: u64,
+    pub put: u64
+}
+
+impl SiteStats {
+    pub fn render(&self) -> String {
+        format!("{{ \"picked\": {}, \"put\": {} }}", self.picked, self.put)
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Metrics {
+    ticks: u64,
+    bots: BTreeMap<String, BotStats>,
+    sites: BTreeMap<String, SiteStats>,
+    units: HashMap<String, u32>,
+    picked: HashMap<String, u32>
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            ticks: 0,
+            bots: BTreeMap::new(),
+            sites: BTreeMap::new(),
+            units: HashMap::new(),
+            picked: HashMap::new()
+        }
+    }
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+    pub fn bot(&self, id: &str) -> Option<&BotStats> {
+        self.bots.get(id)
+    }
+    pub fn site(&self, id: &str) -> Option<&SiteStats> {
+        self.sites.get(id)
+    }
+    fn get_bot(&mut self, id: &str) -> &mut BotStats {
+        self.bots.entry(id.to_owned()).or_insert(BotStats::default())
+    }
+    pub fn record_move(&mut self, id: &str, from: Location, to: Location) {
+        self.get_bot(id).distance += from.manhattan_distance(to) as u64;
+    }
+    pub fn record_blocked(&mut self, id: &str) {
+        self.get_bot(id).blocked += 1;
+    }
+    pub fn record_failed(&mut self, id: &str) {
+        self.get_bot(id).failed += 1;
+    }
+    pub fn sample(&mut self, bots: &[Arc<Mutex<Bot>>], sites: &[Arc<Mutex<Site>>]) {
+        self.ticks += 1;
+        for bot in bots {
+            let mut bot = bot.lock().unwrap();
+            let (id, units, busy) = (bot.id().to_owned(), bot.storage().units(), *bot.get_lock());
+            let changed = self.units.insert(id.clone(), units).map_or(false, |last| last != units);
+            let stats = self.get_bot(&id);
+            match busy {
+                true => stats.busy += 1,
+                false => stats.idle += 1
+            }
+            if changed {
+                stats.transfers += 1;
+            }
+        }
+        for site in sites {
+            let site = site.lock().unwrap();
+            let (id, units) = (site.id().to_owned(), site.pick_storage.units());
+            let last = self.picked.insert(id.clone(), units).unwrap_or(0);
+            let stats = self.sites.entry(id).or_insert(SiteStats::default());
+            if units > last {
+                stats.picked += (units - last) as u64;
+            } else {
+                stats.put += (last - units) as u64;
+            }
+        }
+    }
+    pub fn total(&self) -> BotStats {
+        let mut total = BotStats::default();
+        for stats in self.bots.values() {
+            total.add(stats);
+        }
+        total
+    }
+    pub fn picked(&self) -> u64 {
+        self.sites.values().map(|stats| stats.picked).sum()
+    }
+    pub fn render(&self) -> String {
+        let bots = self.bots.iter()
+            .map(|(id, stats)| format!("{}: {}", quote(id), stats.render()))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let sites = self.sites.iter()
+            .map(|(id, stats)| format!("{}: {}", quote(id), stats.render()))
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("{{ \"ticks\": {}, \"total\": {}, \"picked\": {}, \"bots\": {{ {} }}, \"sites\": {{ {} }} }}",
+            self.ticks, self.total().render(), self.picked(), bots, sites)
+    }
+    pub fn table(&self) -> String {
+        let mut table = format!("{:<12} {:>8} {:>6} {:>6} {:>7} {:>6} {:>9}\n",
+            "bot", "distance", "busy", "idle", "blocked", "failed", "transfers");
+        let total = self.total();
+        for (id, stats) in self.bots.iter().chain(Some((&"total".to_owned(), &total))) {
+            table += &format!("{:<12} {:>8} {:>6} {:>6} {:>7} {:>6} {:>9}\n",
+                id, stats.distance, stats.busy, stats.idle, stats.blocked, stats.failed, stats.transfers);
+        }
+        table += &format!("\n{:<12} {:>8} {:>6}\n", "site", "picked", "put");
+        for (id, stats) in &self.sites {
+            table += &format!("{:<12} {:>8} {:>6}\n", id, stats.picked, stats.put);
+        }
+        table += &format!("\n{} ticks, {} units picked\n", self.ticks, self.picked());
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut metrics = Metrics::new();
+        metrics.record_move("bot-0001", Location::new(0, 0, 0), Location::new(1, 0, 0));
+        metrics.record_move("bot-0001", Location::new(1, 0, 0), Location::new(1, 1, 0));
+        metrics.record_blocked("bot-0001");
+        metrics.record_failed("bot-0002");
+        assert_eq!(metrics.bot("bot-0001").unwrap().distance, 2);
+        assert_eq!(metrics.bot("bot-0001").unwrap().blocked, 1);
+        assert_eq!(metrics.total(), BotStats { distance: 2, blocked: 1, failed: 1, ..BotStats::default() });
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut metrics = Metrics::new();
+        let (bot, site) = (Bot::with_id("bot-0001".to_owned()), Site::with_id("ste-0001".to_owned()));
+        metrics.sample(&[bot.clone()], &[site.clone()]);
+        bot.lock().unwrap().lock().unwrap();
+        bot.lock().unwrap().get_storage().add(1, 2).unwrap();
+        site.lock().unwrap().pick_storage.add(1, 3).unwrap();
+        metrics.sample(&[bot.clone()], &[site.clone()]);
+        site.lock().unwrap().pick_storage.take(1, 1).unwrap();
+        metrics.sample(&[bot.clone()], &[site.clone()]);
+        assert_eq!(metrics.ticks(), 3);
+        assert_eq!(*metrics.bot("bot-0001").unwrap(), BotStats { busy: 2, idle: 1, transfers: 1, ..BotStats::default() });
+        assert_eq!(*metrics.site("ste-0001").unwrap(), SiteStats { picked: 3, put: 1 });
+    }
+
+    #[test]
+    fn test_render() {
+        let mut metrics = Metrics::new();
+        metrics.record_move("bot-0001", Location::new(0, 0, 0), Location::new(1, 0, 0));
+        metrics.sample(&[], &[Site::with_id("ste-0001".to_owned())]);
+        assert_eq!(metrics.render(), "{ \"ticks\": 1, \
+            \"total\": { \"distance\": 1, \"busy\": 0, \"idle\": 0, \"blocked\": 0, \"failed\": 0, \"transfers\": 0 }, \"picked\": 0, \
+            \"bots\": { \"bot-0001\": { \"distance\": 1, \"busy\": 0, \"idle\": 0, \"blocked\": 0, \"failed\": 0, \"transfers\": 0 } }, \
+            \"sites\": { \"ste-0001\": { \"picked\": 0, \"put\": 0 } } }");
+    }
+
+    #[test]
+    fn test_table() {
+        let mut metrics = Metrics::new();
+        metrics.record_move("bot-0001", Location::new(0, 0, 0), Location::new(1, 0, 0));
+        let table = metrics.table();
+        assert!(table.starts_with("bot          distance   busy   idle blocked failed transfers\n"));
+        assert!(table.contains("bot-0001            1      0      0       0      0         0\n"));
+        assert!(table.ends_with("0 ticks, 0 units picked\n"));
+    }
+}
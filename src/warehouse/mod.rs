@@ -1,8 +1,31 @@
 pub mod command;
 pub mod object;
+pub mod dispatcher;
+pub mod trajectory;
 mod world;
 mod storage;
+mod catalog;
+mod reservation;
+mod error;
+mod id;
+mod order;
+mod metrics;
+mod snapshot;
+mod event;
+mod grid;
+mod escape;
 pub use self::storage::Storage;
-pub use self::world::World;
+pub use self::world::{ World, WAIT_LIMIT };
+pub use self::error::Error;
+pub use self::reservation::ReservationTable;
+pub use self::id::IdGenerator;
+pub use self::metrics::Metrics;
+pub use self::order::{ Order, OrderBook, OrderStatus };
 pub use self::storage::Item;
 pub use self::storage::ItemSet;
+pub use self::storage::Capacity;
+pub use self::catalog::{ ItemCatalog, ItemInfo };
+pub use self::snapshot::{ Snapshot, SnapshotContext };
+pub use self::event::{ Event, Observer, EventBus, EventSender, EventLog, EventWriter };
+pub use self::grid::{ GridMap, Cell };
+pub use self::escape::quote;
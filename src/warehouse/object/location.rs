@@ -1,4 +1,65 @@
+use std::fmt;
+use std::slice;
+
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+    Up,
+    Down
+}
+
+impl Direction {
+    pub const ALL: [Direction; 6] = [Direction::West, Direction::East, Direction::North, Direction::South, Direction::Down, Direction::Up];
+
+    pub fn opposite(&self) -> Direction {
+        match *self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up
+        }
+    }
+    pub fn is_vertical(&self) -> bool {
+        *self == Direction::Up || *self == Direction::Down
+    }
+    fn offset(&self) -> (i8, i8, i8) {
+        match *self {
+            Direction::North => (0, -1, 0),
+            Direction::South => (0, 1, 0),
+            Direction::East => (1, 0, 0),
+            Direction::West => (-1, 0, 0),
+            Direction::Up => (0, 0, 1),
+            Direction::Down => (0, 0, -1)
+        }
+    }
+}
+
+pub struct Neighbors {
+    location: Location,
+    directions: slice::Iter<'static, Direction>
+}
+
+impl Iterator for Neighbors {
+    type Item = (Direction, Location);
+    fn next(&mut self) -> Option<(Direction, Location)> {
+        while let Some(&direction) = self.directions.next() {
+            if let Some(next) = self.location.step(direction) {
+                return Some((direction, next));
+            }
+        }
+        None
+    }
+}
+
 #[derive(Hash, Eq, PartialEq, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Location {
     pub x: u32,
     pub y: u32,
@@ -12,43 +73,44 @@ impl Location {
         }
     }
     pub fn nearby(&self, target: Location) -> bool {
-        (self.x as i64 - target.x as i64).abs() + (self.y as i64 - target.y as i64).abs() + (self.z as i64 - target.z as i64).abs() <= 1
+        self.manhattan_distance(target) <= 1
     }
-    pub fn left(&self) -> Self {
-        Self {
-            x: self.x - 1, ..*self
-        }
+    pub fn manhattan_distance(&self, target: Location) -> u32 {
+        ((self.x as i64 - target.x as i64).abs() + (self.y as i64 - target.y as i64).abs() + (self.z as i64 - target.z as i64).abs()) as u32
     }
-    pub fn right(&self) -> Self {
-        Self {
-            x: self.x + 1, ..*self
+    pub fn step(&self, direction: Direction) -> Option<Location> {
+        fn shift(value: u32, delta: i8) -> Option<u32> {
+            match delta {
+                -1 => value.checked_sub(1),
+                1 => value.checked_add(1),
+                _ => Some(value)
+            }
         }
+        let (dx, dy, dz) = direction.offset();
+        Some(Location::new(shift(self.x, dx)?, shift(self.y, dy)?, shift(self.z, dz)?))
     }
-    pub fn front(&self) -> Self {
-        Self {
-            y: self.y + 1, ..*self
+    pub fn neighbors(&self) -> Neighbors {
+        Neighbors {
+            location: *self,
+            directions: Direction::ALL.iter()
         }
     }
-    pub fn back(&self) -> Self {
-        Self {
-            y: self.y - 1, ..*self
-        }
-    }
-    pub fn up(&self) -> Self {
-        Self {
-            z: self.z + 1, ..*self
-        }
-    }
-    pub fn down(&self) -> Self {
-        Self {
-            z: self.z - 1, ..*self
-        }
+    pub fn direction_to(&self, target: Location) -> Option<Direction> {
+        self.neighbors()
+            .find(|&(_, next)| next == target)
+            .map(|(direction, _)| direction)
     }
     pub fn render(&self) -> String {
         format!("{{ \"x\": {}, \"y\": {}, \"z\": {} }}", self.x, self.y, self.z)
     }
 }
 
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,4 +120,39 @@ mod tests {
         let location = Location::new(1, 2, 3);
         assert_eq!(location.render(), "{ \"x\": 1, \"y\": 2, \"z\": 3 }");
     }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(Location::new(1, 2, 3).manhattan_distance(Location::new(3, 0, 3)), 4);
+        assert_eq!(Location::new(1, 2, 3).manhattan_distance(Location::new(1, 2, 3)), 0);
+    }
+
+    #[test]
+    fn test_step() {
+        let origin = Location::new(0, 0, 0);
+        assert_eq!(origin.step(Direction::West), None);
+        assert_eq!(origin.step(Direction::North), None);
+        assert_eq!(origin.step(Direction::Down), None);
+        assert_eq!(origin.step(Direction::South), Some(Location::new(0, 1, 0)));
+        assert_eq!(Location::new(u32::max_value(), 0, 0).step(Direction::East), None);
+        assert_eq!(Location::new(1, 1, 1).step(Direction::Up).unwrap().step(Direction::Up.opposite()), Some(Location::new(1, 1, 1)));
+    }
+
+    #[test]
+    fn test_neighbors() {
+        assert_eq!(Location::new(0, 0, 0).neighbors().map(|(_, next)| next).collect::<Vec<Location>>(),
+            vec![Location::new(1, 0, 0), Location::new(0, 1, 0), Location::new(0, 0, 1)]);
+        assert_eq!(Location::new(1, 1, 1).neighbors().count(), 6);
+        assert_eq!(Location::new(1, 1, 0).direction_to(Location::new(1, 0, 0)), Some(Direction::North));
+        assert_eq!(Location::new(1, 1, 0).direction_to(Location::new(2, 2, 0)), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let location = Location::new(1, 2, 3);
+        let value = ::serde_json::to_value(&location).unwrap();
+        assert_eq!(value, ::serde_json::from_str::<::serde_json::Value>(&location.render()).unwrap());
+        assert_eq!(::serde_json::from_value::<Location>(value).unwrap(), location);
+    }
 }
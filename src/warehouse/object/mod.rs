@@ -11,4 +11,4 @@ pub use self::shelf::Shelf;
 pub use self::site::Site;
 pub use self::object::Object;
 pub use self::test_object::TestObject;
-pub use self::location::Location;
\ No newline at end of file
+pub use self::location::{ Location, Direction };
\ No newline at end of file
@@ -1,5 +1,9 @@
-use warehouse::Storage;
+use warehouse::{ Storage, Event, Error, quote };
 use warehouse::object::Location;
+#[cfg(feature = "serde")]
+use serde::{ Serialize, Serializer };
+#[cfg(feature = "serde")]
+use serde::ser;
 
 pub trait Object {
     fn id(&self) -> &str;
@@ -8,30 +12,54 @@ pub trait Object {
     fn get_storage(&mut self) -> &mut Storage;
     fn get_location(&mut self) -> &mut Location;
     fn get_lock(&mut self) -> &mut bool;
-    fn lock(&mut self) -> Result<(), &'static str> {
+    fn pick_storage(&self) -> Option<&Storage> {
+        None
+    }
+    fn get_pick_storage(&mut self) -> Option<&mut Storage> {
+        None
+    }
+    fn drive_under(&self) -> bool {
+        false
+    }
+    fn lock(&mut self) -> Result<(), Error> {
+        let id = self.id().to_owned();
         let locked = self.get_lock();
         match *locked {
-            true => Err("object already locked"),
-            false => {
-                *locked = true;
-                Ok(())
-            }
+            true => return Err(Error::AlreadyLocked { id: id }),
+            false => *locked = true
         }
+        if let Some(events) = self.storage().events() {
+            events.emit(Event::ObjectLocked { id: id });
+        }
+        Ok(())
     }
-    fn unlock(&mut self) -> Result<(), &'static str> {
+    fn unlock(&mut self) -> Result<(), Error> {
+        let id = self.id().to_owned();
         let locked = self.get_lock();
         match *locked {
-            false => Err("object already unlocked"),
-            true => {
-                *locked = false;
-                Ok(())
-            }
-        } 
-    }
-    fn render(&self) -> Result<String, &'static str> {
-        Ok(format!("{{ \"id\": \"{}\", \"storage\": {}, \"location\": {} }}", 
-                        self.id(),
+            false => return Err(Error::AlreadyUnlocked { id: id }),
+            true => *locked = false
+        }
+        if let Some(events) = self.storage().events() {
+            events.emit(Event::ObjectUnlocked { id: id });
+        }
+        Ok(())
+    }
+    fn render(&self) -> Result<String, Error> {
+        Ok(format!("{{ \"id\": {}, \"storage\": {}, \"location\": {} }}", 
+                        quote(self.id()),
                         self.storage().render()?,
                         self.location().render()))
     }
+    #[cfg(feature = "serde")]
+    fn to_value(&self) -> Result<::serde_json::Value, Error> {
+        ::serde_json::from_str(&self.render()?).map_err(|_| Error::Other("object rendered invalid json"))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Object {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        self.to_value().map_err(ser::Error::custom)?.serialize(serializer)
+    }
 }
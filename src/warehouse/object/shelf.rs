@@ -6,17 +6,23 @@ use warehouse::command::Command;
 use std::sync::{ Arc, Mutex };
 use self::uuid::Uuid;
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Shelf {
     id: String,
     location: Location,
+    #[cfg_attr(feature = "serde", serde(skip))]
     locked: bool,
     storage: Storage
 }
 
 impl Shelf {
     pub fn new() -> Arc<Mutex<Self>> {
+        Self::with_id(format!("slf-{}", Uuid::new_v4()))
+    }
+    pub fn with_id(id: String) -> Arc<Mutex<Self>> {
         Arc::new(Mutex::new(Self {
-            id: format!("slf-{}", Uuid::new_v4()),
+            id: id,
             location: Location { x: 0, y: 0, z: 0 },
             locked: false,
             storage: Storage::new()
@@ -43,6 +49,9 @@ impl Object for Shelf {
     fn get_lock(&mut self) -> &mut bool {
         &mut self.locked
     }
+    fn drive_under(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -56,4 +65,18 @@ mod tests {
         assert_eq!(&obj.id()[..4], "slf-");
         Uuid::parse_str(&obj.id()[4..]).unwrap();
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let obj = Shelf::new();
+        let mut obj = obj.lock().unwrap();
+        obj.get_storage().add(1, 2).unwrap();
+        let value = ::serde_json::to_value(&*obj).unwrap();
+        assert_eq!(value, ::serde_json::from_str::<::serde_json::Value>(&obj.render().unwrap()).unwrap());
+        assert_eq!(obj.to_value().unwrap(), value);
+        let loaded = ::serde_json::from_value::<Shelf>(value).unwrap();
+        assert_eq!(loaded.id(), obj.id());
+        assert_eq!(loaded.storage().items, vec![(1, 2)]);
+    }
 }
@@ -1,13 +1,16 @@
 extern crate uuid;
 
 use warehouse::object::{ Object, Location };
-use warehouse::Storage;
+use warehouse::{ Storage, Error, quote };
 use std::sync::{ Arc, Mutex };
 use self::uuid::Uuid;
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Site {
     id: String,
     location: Location,
+    #[cfg_attr(feature = "serde", serde(skip))]
     locked: bool,
     storage: Storage,
     pub pick_storage: Storage
@@ -15,8 +18,11 @@ pub struct Site {
 
 impl Site {
     pub fn new() -> Arc<Mutex<Self>> {
+        Self::with_id(format!("ste-{}", Uuid::new_v4()))
+    }
+    pub fn with_id(id: String) -> Arc<Mutex<Self>> {
         Arc::new(Mutex::new(Self {
-            id: format!("ste-{}", Uuid::new_v4()),
+            id: id,
             location: Location { x: 0, y: 0, z: 0 },
             locked: false,
             storage: Storage::new(),
@@ -44,9 +50,15 @@ impl Object for Site {
     fn get_lock(&mut self) -> &mut bool {
         &mut self.locked
     }
-    fn render(&self) -> Result<String, &'static str> {
-        Ok(format!("{{ \"id\": \"{}\", \"storage\": {}, \"pick_storage\": {}, \"location\": {} }}", 
-                    self.id(),
+    fn pick_storage(&self) -> Option<&Storage> {
+        Some(&self.pick_storage)
+    }
+    fn get_pick_storage(&mut self) -> Option<&mut Storage> {
+        Some(&mut self.pick_storage)
+    }
+    fn render(&self) -> Result<String, Error> {
+        Ok(format!("{{ \"id\": {}, \"storage\": {}, \"pick_storage\": {}, \"location\": {} }}", 
+                    quote(self.id()),
                     self.storage().render()?,
                     self.pick_storage.render()?,
                     self.location().render()))
@@ -72,4 +84,17 @@ mod tests {
         assert_eq!(obj.render().unwrap(), 
             format!("{{ \"id\": \"{}\", \"storage\": [], \"pick_storage\": [], \"location\": {{ \"x\": 0, \"y\": 0, \"z\": 0 }} }}", obj.id()));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let obj = Site::new();
+        let mut obj = obj.lock().unwrap();
+        obj.pick_storage.add(3, 4).unwrap();
+        let value = ::serde_json::to_value(&*obj).unwrap();
+        assert_eq!(value, ::serde_json::from_str::<::serde_json::Value>(&obj.render().unwrap()).unwrap());
+        assert_eq!(obj.to_value().unwrap(), value);
+        let loaded = ::serde_json::from_value::<Site>(value).unwrap();
+        assert_eq!(loaded.pick_storage.items, vec![(3, 4)]);
+    }
 }
@@ -64,7 +64,7 @@ mod tests {
         location.x = 233;
     }
     #[test]
-     #[should_panic(expected="object already locked")]
+    #[should_panic(expected="AlreadyLocked")]
     fn test_lock() {
         let obj = TestObject::new();
         let mut obj = obj.lock().unwrap();
@@ -73,7 +73,7 @@ mod tests {
         
     }
     #[test]
-    #[should_panic(expected="object already unlocked")]
+    #[should_panic(expected="AlreadyUnlocked")]
     fn test_unlock() {
         let obj = TestObject::new();
         let mut obj = obj.lock().unwrap();
@@ -0,0 +1,374 @@
+use warehouse::{ IdGenerator, ItemSet, Error, quote };
+#[cfg(feature = "serde")]
+use warehouse::Item;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum OrderStatus {
+    Pending,
+    Assigned,
+    Complete
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OrderStatus::Pending => write!(f, "pending"),
+            OrderStatus::Assigned => write!(f, "assigned"),
+            OrderStatus::Complete => write!(f, "complete")
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Order {
+    pub id: String,
+    pub lines: Vec<ItemSet>,
+    pub priority: u32,
+    pub due: u64,
+    pub status: OrderStatus,
+    pub site: Option<String>,
+    pub created: u64,
+    pub completed: Option<u64>,
+    remaining: Vec<ItemSet>
+}
+
+impl Order {
+    pub fn remaining(&self) -> &Vec<ItemSet> {
+        &self.remaining
+    }
+    pub fn is_late(&self) -> bool {
+        match self.completed {
+            Some(tick) => tick > self.due,
+            None => false
+        }
+    }
+    pub fn render(&self) -> String {
+        let render = |lines: &Vec<ItemSet>| lines.iter()
+            .map(|set: &ItemSet| format!("{{ \"item\": {}, \"count\": {} }}", set.0, set.1))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let site = match self.site {
+            Some(ref site) => quote(site),
+            None => "null".to_owned()
+        };
+        let completed = match self.completed {
+            Some(tick) => tick.to_string(),
+            None => "null".to_owned()
+        };
+        format!("{{ \"id\": {}, \"lines\": [{}], \"priority\": {}, \"due\": {}, \"status\": \"{}\", \"site\": {}, \"remaining\": [{}], \"created\": {}, \"completed\": {} }}",
+            quote(&self.id), render(&self.lines), self.priority, self.due, self.status, site, render(&self.remaining), self.created, completed)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct LineRecord {
+    item: Item,
+    count: u32
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct OrderRecord {
+    id: String,
+    lines: Vec<LineRecord>,
+    priority: u32,
+    due: u64,
+    status: OrderStatus,
+    site: Option<String>,
+    remaining: Vec<LineRecord>,
+    created: u64,
+    completed: Option<u64>
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OrderBook {
+    orders: Vec<Order>,
+    ids: IdGenerator,
+    tick: u64
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self {
+            orders: Vec::new(),
+            ids: IdGenerator::sequential(),
+            tick: 0
+        }
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_render(value: &str, tick: u64) -> Result<Self, Error> {
+        let records = ::serde_json::from_str::<Vec<OrderRecord>>(value).map_err(|err| Error::Checkpoint(err.to_string()))?;
+        let lines = |lines: Vec<LineRecord>| lines.into_iter().map(|line| (line.item, line.count)).collect::<Vec<ItemSet>>();
+        let mut ids = IdGenerator::sequential();
+        let orders = records.into_iter()
+            .map(|record| {
+                ids.observe(&record.id);
+                Order {
+                    id: record.id,
+                    lines: lines(record.lines),
+                    priority: record.priority,
+                    due: record.due,
+                    status: record.status,
+                    site: record.site,
+                    created: record.created,
+                    completed: record.completed,
+                    remaining: lines(record.remaining)
+                }
+            })
+            .collect();
+        Ok(Self {
+            orders: orders,
+            ids: ids,
+            tick: tick
+        })
+    }
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+    pub fn advance_tick(&mut self) {
+        self.tick += 1;
+    }
+    pub fn add(&mut self, lines: Vec<ItemSet>, priority: u32, due: u64) -> Result<String, Error> {
+        let mut merged: Vec<ItemSet> = Vec::new();
+        for (item, count) in lines {
+            if count == 0 {
+                return Err(Error::InvalidOrderLine { item: item, count: count });
+            }
+            match merged.iter_mut().find(|line| line.0 == item) {
+                Some(line) => line.1 = line.1.checked_add(count).ok_or(Error::InvalidOrderLine { item: item, count: count })?,
+                None => merged.push((item, count))
+            }
+        }
+        let lines = merged;
+        let id = self.ids.next("ord");
+        self.orders.push(Order {
+            id: id.clone(),
+            remaining: lines.clone(),
+            lines: lines,
+            priority: priority,
+            due: due,
+            status: OrderStatus::Pending,
+            site: None,
+            created: self.tick,
+            completed: None
+        });
+        Ok(id)
+    }
+    pub fn orders(&self) -> &Vec<Order> {
+        &self.orders
+    }
+    pub fn get(&self, id: &str) -> Option<&Order> {
+        self.orders.iter().find(|order| order.id == id)
+    }
+    fn get_mut(&mut self, id: &str) -> Result<&mut Order, Error> {
+        self.orders.iter_mut().find(|order| order.id == id).ok_or(Error::UnknownOrder { id: id.to_owned() })
+    }
+    pub fn pending(&self) -> Vec<&Order> {
+        let mut pending = self.orders.iter()
+            .filter(|order| order.status == OrderStatus::Pending)
+            .collect::<Vec<&Order>>();
+        pending.sort_by_key(|order| (!order.priority, order.due));
+        pending
+    }
+    pub fn assigned(&self) -> Vec<&Order> {
+        self.orders.iter().filter(|order| order.status == OrderStatus::Assigned).collect()
+    }
+    pub fn active(&self, site: &str) -> Option<&Order> {
+        self.orders.iter().find(|order| order.status == OrderStatus::Assigned && order.site.as_ref().map_or(false, |id| id == site))
+    }
+    pub fn assign(&mut self, id: &str, site: &str) -> Result<(), Error> {
+        let order = self.get_mut(id)?;
+        if order.status != OrderStatus::Pending {
+            return Err(Error::InvalidOrderStatus { id: id.to_owned(), status: order.status });
+        }
+        order.status = OrderStatus::Assigned;
+        order.site = Some(site.to_owned());
+        Ok(())
+    }
+    pub fn release(&mut self, id: &str) -> Result<(), Error> {
+        let order = self.get_mut(id)?;
+        if order.status != OrderStatus::Assigned {
+            return Err(Error::InvalidOrderStatus { id: id.to_owned(), status: order.status });
+        }
+        order.status = OrderStatus::Pending;
+        order.site = None;
+        Ok(())
+    }
+    pub fn check_fulfill(&self, id: &str, set: ItemSet) -> Result<(), Error> {
+        let order = self.get(id).ok_or(Error::UnknownOrder { id: id.to_owned() })?;
+        if order.status != OrderStatus::Assigned {
+            return Err(Error::InvalidOrderStatus { id: id.to_owned(), status: order.status });
+        }
+        let (item, count) = set;
+        let have = order.remaining.iter().find(|line| line.0 == item).map_or(0, |line| line.1);
+        if have < count {
+            return Err(Error::NotEnoughItem { item: item, have: have, want: count });
+        }
+        Ok(())
+    }
+    pub fn fulfill(&mut self, id: &str, set: ItemSet) -> Result<bool, Error> {
+        self.check_fulfill(id, set)?;
+        let tick = self.tick;
+        let order = self.get_mut(id)?;
+        let (item, count) = set;
+        if let Some(line) = order.remaining.iter_mut().find(|line| line.0 == item) {
+            line.1 -= count.min(line.1);
+        }
+        order.remaining.retain(|line| line.1 > 0);
+        if order.remaining.len() > 0 {
+            return Ok(false);
+        }
+        order.status = OrderStatus::Complete;
+        order.completed = Some(tick);
+        Ok(true)
+    }
+    pub fn completed(&self) -> usize {
+        self.orders.iter().filter(|order| order.status == OrderStatus::Complete).count()
+    }
+    pub fn late(&self) -> usize {
+        self.orders.iter().filter(|order| order.is_late()).count()
+    }
+    pub fn throughput(&self) -> f64 {
+        match self.tick {
+            0 => 0.0,
+            tick => self.completed() as f64 / tick as f64
+        }
+    }
+    pub fn render(&self) -> String {
+        let orders = self.orders.iter()
+            .map(|order: &Order| order.render())
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("[{}]", orders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut orders = OrderBook::new();
+        assert_eq!(orders.add(vec![(1, 2)], 0, 10).unwrap(), "ord-0001");
+        assert_eq!(orders.add(vec![(2, 1)], 0, 10).unwrap(), "ord-0002");
+        assert_eq!(orders.get("ord-0001").unwrap().status, OrderStatus::Pending);
+    }
+
+    #[test]
+    fn test_pending_order() {
+        let mut orders = OrderBook::new();
+        orders.add(vec![(1, 1)], 0, 5).unwrap();
+        orders.add(vec![(1, 1)], 1, 20).unwrap();
+        orders.add(vec![(1, 1)], 1, 10).unwrap();
+        orders.add(vec![(1, 1)], 0, 3).unwrap();
+        let pending = orders.pending().iter().map(|order| order.id.clone()).collect::<Vec<String>>();
+        assert_eq!(pending, vec!["ord-0003", "ord-0002", "ord-0004", "ord-0001"]);
+    }
+
+    #[test]
+    fn test_release() {
+        let mut orders = OrderBook::new();
+        let id = orders.add(vec![(1, 1)], 0, 5).unwrap();
+        orders.assign(&id, "ste-0001").unwrap();
+        orders.release(&id).unwrap();
+        assert!(orders.active("ste-0001").is_none());
+        assert_eq!(orders.pending().len(), 1);
+        assert!(orders.release(&id).is_err());
+    }
+
+    #[test]
+    fn test_assign() {
+        let mut orders = OrderBook::new();
+        let id = orders.add(vec![(1, 1)], 0, 5).unwrap();
+        orders.assign(&id, "ste-0001").unwrap();
+        assert_eq!(orders.active("ste-0001").unwrap().id, id);
+        assert!(orders.active("ste-0002").is_none());
+        assert_eq!(orders.pending().len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected="InvalidOrderStatus")]
+    fn test_assign_twice() {
+        let mut orders = OrderBook::new();
+        let id = orders.add(vec![(1, 1)], 0, 5).unwrap();
+        orders.assign(&id, "ste-0001").unwrap();
+        orders.assign(&id, "ste-0002").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected="UnknownOrder")]
+    fn test_assign_unknown() {
+        OrderBook::new().assign("ord-0001", "ste-0001").unwrap();
+    }
+
+    #[test]
+    fn test_fulfill() {
+        let mut orders = OrderBook::new();
+        let id = orders.add(vec![(1, 2), (2, 1)], 0, 1).unwrap();
+        orders.assign(&id, "ste-0001").unwrap();
+        assert!(!orders.fulfill(&id, (1, 2)).unwrap());
+        orders.advance_tick();
+        orders.advance_tick();
+        assert!(orders.fulfill(&id, (2, 1)).unwrap());
+        let order = orders.get(&id).unwrap();
+        assert_eq!(order.status, OrderStatus::Complete);
+        assert_eq!(order.completed, Some(2));
+        assert!(order.is_late());
+        assert_eq!(orders.completed(), 1);
+        assert_eq!(orders.late(), 1);
+        assert_eq!(orders.throughput(), 0.5);
+    }
+
+    #[test]
+    fn test_add_lines() {
+        let mut orders = OrderBook::new();
+        let id = orders.add(vec![(1, 2), (2, 1), (1, 1)], 0, 10).unwrap();
+        assert_eq!(orders.get(&id).unwrap().lines, vec![(1, 3), (2, 1)]);
+        orders.assign(&id, "ste-0001").unwrap();
+        assert!(!orders.fulfill(&id, (1, 2)).unwrap());
+        assert_eq!(orders.get(&id).unwrap().remaining(), &vec![(1, 1), (2, 1)]);
+        assert_eq!(orders.add(vec![(1, 1), (2, 0)], 0, 10), Err(Error::InvalidOrderLine { item: 2, count: 0 }));
+        assert_eq!(orders.orders().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected="NotEnoughItem")]
+    fn test_fulfill_too_many() {
+        let mut orders = OrderBook::new();
+        let id = orders.add(vec![(1, 2)], 0, 1).unwrap();
+        orders.assign(&id, "ste-0001").unwrap();
+        orders.fulfill(&id, (1, 3)).unwrap();
+    }
+
+    #[test]
+    fn test_render() {
+        let mut orders = OrderBook::new();
+        let id = orders.add(vec![(1, 2)], 1, 8).unwrap();
+        assert_eq!(orders.render(), "[{ \"id\": \"ord-0001\", \"lines\": [{ \"item\": 1, \"count\": 2 }], \"priority\": 1, \"due\": 8, \"status\": \"pending\", \"site\": null, \"remaining\": [{ \"item\": 1, \"count\": 2 }], \"created\": 0, \"completed\": null }]");
+        orders.assign(&id, "ste-0001").unwrap();
+        assert_eq!(orders.get(&id).unwrap().render(), "{ \"id\": \"ord-0001\", \"lines\": [{ \"item\": 1, \"count\": 2 }], \"priority\": 1, \"due\": 8, \"status\": \"assigned\", \"site\": \"ste-0001\", \"remaining\": [{ \"item\": 1, \"count\": 2 }], \"created\": 0, \"completed\": null }");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_render() {
+        let mut orders = OrderBook::new();
+        let id = orders.add(vec![(1, 2), (2, 1)], 1, 8).unwrap();
+        orders.add(vec![(3, 1)], 0, 9).unwrap();
+        orders.assign(&id, "ste-0001").unwrap();
+        orders.fulfill(&id, (1, 2)).unwrap();
+        let mut loaded = OrderBook::from_render(&orders.render(), 5).unwrap();
+        assert_eq!(loaded.render(), orders.render());
+        assert_eq!(loaded.tick(), 5);
+        assert_eq!(loaded.get(&id).unwrap().remaining(), &vec![(2, 1)]);
+        assert_eq!(loaded.add(vec![(1, 1)], 0, 10).unwrap(), "ord-0003");
+        assert!(OrderBook::from_render("[{ \"id\": \"ord-0001\" }]", 0).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use warehouse::object::Location;
+use warehouse::Error;
+use std::collections::HashMap;
+#[cfg(feature = "serde")]
+use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+
+#[derive(Clone)]
+pub struct ReservationTable {
+    cells: HashMap<(Location, u64), String>,
+    parked: HashMap<Location, (u64, String)>,
+    edges: HashMap<String, (Location, Location)>
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct Cell {
+    location: Location,
+    tick: u64,
+    id: String
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct Edge {
+    id: String,
+    from: Location,
+    to: Location
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct Cells {
+    cells: Vec<Cell>,
+    parked: Vec<Cell>,
+    #[serde(default)]
+    edges: Vec<Edge>
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ReservationTable {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut cells = self.cells.iter()
+            .map(|(&(location, tick), id)| Cell { location: location, tick: tick, id: id.clone() })
+            .collect::<Vec<Cell>>();
+        cells.sort_by_key(|cell| (cell.tick, cell.location.z, cell.location.y, cell.location.x));
+        let mut parked = self.parked.iter()
+            .map(|(&location, &(tick, ref id))| Cell { location: location, tick: tick, id: id.clone() })
+            .collect::<Vec<Cell>>();
+        parked.sort_by_key(|cell| (cell.location.z, cell.location.y, cell.location.x));
+        let mut edges = self.edges.iter()
+            .map(|(id, &(from, to))| Edge { id: id.clone(), from: from, to: to })
+            .collect::<Vec<Edge>>();
+        edges.sort_by(|a, b| a.id.cmp(&b.id));
+        Cells {
+            cells: cells,
+            parked: parked,
+            edges: edges
+        }.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ReservationTable {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let cells = Cells::deserialize(deserializer)?;
+        Ok(Self {
+            cells: cells.cells.into_iter().map(|cell| ((cell.location, cell.tick), cell.id)).collect(),
+            parked: cells.parked.into_iter().map(|cell| (cell.location, (cell.tick, cell.id))).collect(),
+            edges: cells.edges.into_iter().map(|edge| (edge.id, (edge.from, edge.to))).collect()
+        })
+    }
+}
+
+impl ReservationTable {
+    pub fn new() -> Self {
+        Self {
+            cells: HashMap::new(),
+            parked: HashMap::new(),
+            edges: HashMap::new()
+        }
+    }
+    pub fn owner(&self, location: Location, tick: u64) -> Option<&str> {
+        if let Some(id) = self.cells.get(&(location, tick)) {
+            return Some(id);
+        }
+        match self.parked.get(&location) {
+            Some((since, id)) if *since <= tick => Some(id),
+            _ => None
+        }
+    }
+    pub fn is_free(&self, id: &str, location: Location, tick: u64) -> bool {
+        match self.owner(location, tick) {
+            Some(owner) => owner == id,
+            None => true
+        }
+    }
+    pub fn check_move(&self, id: &str, from: Location, to: Location, tick: u64) -> Result<(), Error> {
+        if let Some(owner) = self.owner(to, tick + 1) {
+            if owner != id {
+                return Err(Error::LocationReserved { location: to, tick: tick + 1, by: owner.to_owned() });
+            }
+        }
+        if from != to {
+            if let Some(other) = self.owner(to, tick) {
+                if other != id && self.owner(from, tick + 1) == Some(other) {
+                    return Err(Error::SwapConflict { location: to, by: other.to_owned() });
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn free_from(&self, id: &str, location: Location) -> Option<u64> {
+        if let Some((_, owner)) = self.parked.get(&location) {
+            if owner != id {
+                return None;
+            }
+        }
+        Some(self.cells.iter()
+            .filter(|&(&(c_location, _), owner)| c_location == location && owner != id)
+            .map(|(&(_, tick), _)| tick + 1)
+            .max()
+            .unwrap_or(0))
+    }
+    pub fn reserve(&mut self, id: &str, path: &[Location], tick: u64) -> Result<(), Error> {
+        for (idx, location) in path.iter().enumerate() {
+            if let Some(owner) = self.owner(*location, tick + idx as u64) {
+                if owner != id {
+                    return Err(Error::LocationReserved { location: *location, tick: tick + idx as u64, by: owner.to_owned() });
+                }
+            }
+        }
+        for (idx, location) in path.iter().enumerate() {
+            self.cells.insert((*location, tick + idx as u64), id.to_owned());
+        }
+        if let Some(location) = path.last() {
+            self.parked.insert(*location, (tick + path.len() as u64 - 1, id.to_owned()));
+        }
+        Ok(())
+    }
+    pub fn reserve_edge(&mut self, id: &str, from: Location, to: Location) -> Result<(), Error> {
+        if let Some((other, _)) = self.edges.iter().find(|&(other, &edge)| other != id && edge == (to, from)) {
+            return Err(Error::SwapConflict { location: to, by: other.clone() });
+        }
+        self.edges.insert(id.to_owned(), (from, to));
+        Ok(())
+    }
+    pub fn release(&mut self, id: &str) {
+        self.cells.retain(|_, owner| owner != id);
+        self.parked.retain(|_, parked| parked.1 != id);
+        self.edges.remove(id);
+    }
+    pub fn expire(&mut self, tick: u64) {
+        self.cells.retain(|&(_, c_tick), _| c_tick >= tick);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve() {
+        let mut table = ReservationTable::new();
+        let path = vec![Location::new(0, 0, 0), Location::new(1, 0, 0)];
+        table.reserve("bot-1", &path, 3).unwrap();
+        assert_eq!(table.owner(Location::new(0, 0, 0), 3), Some("bot-1"));
+        assert_eq!(table.owner(Location::new(0, 0, 0), 4), None);
+        assert_eq!(table.owner(Location::new(1, 0, 0), 4), Some("bot-1"));
+        assert_eq!(table.owner(Location::new(1, 0, 0), 100), Some("bot-1"));
+        assert!(table.is_free("bot-1", Location::new(1, 0, 0), 4));
+        assert!(!table.is_free("bot-2", Location::new(1, 0, 0), 4));
+    }
+
+    #[test]
+    #[should_panic(expected="LocationReserved")]
+    fn test_reserve_conflict() {
+        let mut table = ReservationTable::new();
+        table.reserve("bot-1", &vec![Location::new(0, 0, 0), Location::new(1, 0, 0)], 0).unwrap();
+        table.reserve("bot-2", &vec![Location::new(2, 0, 0), Location::new(1, 0, 0)], 0).unwrap();
+    }
+
+    #[test]
+    fn test_check_move() {
+        let mut table = ReservationTable::new();
+        table.reserve("bot-1", &vec![Location::new(0, 0, 0), Location::new(0, 1, 0)], 0).unwrap();
+        assert_eq!(table.check_move("bot-2", Location::new(0, 2, 0), Location::new(0, 1, 0), 0),
+            Err(Error::LocationReserved { location: Location::new(0, 1, 0), tick: 1, by: "bot-1".to_owned() }));
+        assert_eq!(table.check_move("bot-2", Location::new(0, 1, 0), Location::new(0, 0, 0), 0),
+            Err(Error::SwapConflict { location: Location::new(0, 0, 0), by: "bot-1".to_owned() }));
+        assert_eq!(table.check_move("bot-2", Location::new(1, 0, 0), Location::new(0, 0, 0), 0), Ok(()));
+    }
+
+    #[test]
+    fn test_release_and_expire() {
+        let mut table = ReservationTable::new();
+        table.reserve("bot-1", &vec![Location::new(0, 0, 0), Location::new(1, 0, 0)], 0).unwrap();
+        table.reserve("bot-2", &vec![Location::new(5, 0, 0), Location::new(6, 0, 0)], 0).unwrap();
+        table.expire(1);
+        assert_eq!(table.owner(Location::new(0, 0, 0), 0), None);
+        table.release("bot-1");
+        assert_eq!(table.owner(Location::new(1, 0, 0), 1), None);
+        assert_eq!(table.owner(Location::new(6, 0, 0), 1), Some("bot-2"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut table = ReservationTable::new();
+        table.reserve("bot-1", &vec![Location::new(0, 0, 0), Location::new(1, 0, 0)], 2).unwrap();
+        let value = ::serde_json::to_value(&table).unwrap();
+        let loaded = ::serde_json::from_value::<ReservationTable>(value).unwrap();
+        assert_eq!(loaded.cells, table.cells);
+        assert_eq!(loaded.parked, table.parked);
+        assert_eq!(loaded.edges, table.edges);
+    }
+
+    #[test]
+    fn test_free_from() {
+        let mut table = ReservationTable::new();
+        table.reserve("bot-1", &vec![Location::new(0, 0, 0), Location::new(1, 0, 0), Location::new(2, 0, 0)], 0).unwrap();
+        assert_eq!(table.free_from("bot-2", Location::new(1, 0, 0)), Some(2));
+        assert_eq!(table.free_from("bot-2", Location::new(2, 0, 0)), None);
+        assert_eq!(table.free_from("bot-2", Location::new(3, 0, 0)), Some(0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_order() {
+        let mut table = ReservationTable::new();
+        for x in (0..8).rev() {
+            let id = format!("bot-{}", x);
+            table.reserve(&id, &vec![Location::new(x, 0, 0), Location::new(x, 1, 0)], 0).unwrap();
+            table.reserve_edge(&id, Location::new(x, 0, 0), Location::new(x, 1, 0)).unwrap();
+        }
+        let value = ::serde_json::to_value(&table).unwrap();
+        let ticks = value["cells"].as_array().unwrap().iter()
+            .map(|cell| (cell["tick"].as_u64().unwrap(), cell["location"]["x"].as_u64().unwrap()))
+            .collect::<Vec<(u64, u64)>>();
+        assert_eq!(ticks, (0..2).flat_map(|tick| (0..8).map(move |x| (tick, x))).collect::<Vec<(u64, u64)>>());
+        let parked = value["parked"].as_array().unwrap().iter().map(|cell| cell["location"]["x"].as_u64().unwrap()).collect::<Vec<u64>>();
+        assert_eq!(parked, (0..8).collect::<Vec<u64>>());
+        let edges = value["edges"].as_array().unwrap().iter().map(|edge| edge["id"].as_str().unwrap().to_owned()).collect::<Vec<String>>();
+        assert_eq!(edges, (0..8).map(|x| format!("bot-{}", x)).collect::<Vec<String>>());
+    }
+
+    #[test]
+    fn test_reserve_edge() {
+        let mut table = ReservationTable::new();
+        let (loc1, loc2) = (Location::new(0, 0, 0), Location::new(0, 1, 0));
+        table.reserve_edge("bot-1", loc1, loc2).unwrap();
+        table.reserve_edge("bot-2", loc2, Location::new(1, 1, 0)).unwrap();
+        assert_eq!(table.reserve_edge("bot-3", loc2, loc1), Err(Error::SwapConflict { location: loc1, by: "bot-1".to_owned() }));
+        table.release("bot-1");
+        table.reserve_edge("bot-3", loc2, loc1).unwrap();
+    }
+}
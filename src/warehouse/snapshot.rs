@@ -0,0 +1,375 @@
+use warehouse::{ World, OrderBook, Error };
+use warehouse::object::{ Object, Bot, Shelf, Site };
+use warehouse::command::{ Command, ParallelCommandQueue };
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use warehouse::{ Storage, Capacity, ItemSet };
+#[cfg(feature = "serde")]
+use warehouse::object::Location;
+#[cfg(feature = "serde")]
+use serde_json::{ self, Value };
+#[cfg(feature = "serde")]
+use std::io::{ self, Read, Write };
+use std::collections::{ HashMap, HashSet };
+use std::sync::{ Arc, Mutex };
+
+pub struct SnapshotContext {
+    world: Arc<Mutex<World>>,
+    orders: Arc<Mutex<OrderBook>>,
+    objects: HashMap<String, Arc<Mutex<Object>>>,
+    bots: HashMap<String, Arc<Mutex<Bot>>>,
+    shelves: HashMap<String, Arc<Mutex<Shelf>>>,
+    sites: HashMap<String, Arc<Mutex<Site>>>
+}
+
+pub struct Transaction {
+    world: World,
+    orders: Option<OrderBook>,
+    bots: Vec<(Arc<Mutex<Bot>>, Bot)>,
+    shelves: Vec<(Arc<Mutex<Shelf>>, Shelf)>,
+    sites: Vec<(Arc<Mutex<Site>>, Site)>,
+    commands: Vec<Box<Command>>,
+    events: usize
+}
+
+fn backup<T: Clone>(items: &HashMap<String, Arc<Mutex<T>>>, touched: &Option<HashSet<String>>) -> Vec<(Arc<Mutex<T>>, T)> {
+    items.iter()
+        .filter(|&(id, _)| touched.as_ref().map_or(true, |touched| touched.contains(id)))
+        .map(|(_, item)| (item.clone(), item.lock().unwrap().clone()))
+        .collect()
+}
+
+fn rollback<T>(items: Vec<(Arc<Mutex<T>>, T)>) {
+    for (item, state) in items {
+        *item.lock().unwrap() = state;
+    }
+}
+
+impl Transaction {
+    pub fn rollback(self, context: &SnapshotContext) -> Vec<Box<Command>> {
+        let mut world = context.world.lock().unwrap();
+        *world = self.world;
+        world.discard_events(self.events);
+        drop(world);
+        if let Some(orders) = self.orders {
+            *context.orders.lock().unwrap() = orders;
+        }
+        rollback(self.bots);
+        rollback(self.shelves);
+        rollback(self.sites);
+        self.commands
+    }
+}
+
+impl SnapshotContext {
+    pub fn new(world: Arc<Mutex<World>>, orders: Arc<Mutex<OrderBook>>, bots: &[Arc<Mutex<Bot>>], shelves: &[Arc<Mutex<Shelf>>], sites: &[Arc<Mutex<Site>>]) -> Self {
+        let mut objects: HashMap<String, Arc<Mutex<Object>>> = HashMap::new();
+        for bot in bots {
+            objects.insert(bot.lock().unwrap().id().to_owned(), bot.clone());
+        }
+        for shelf in shelves {
+            objects.insert(shelf.lock().unwrap().id().to_owned(), shelf.clone());
+        }
+        for site in sites {
+            objects.insert(site.lock().unwrap().id().to_owned(), site.clone());
+        }
+        Self {
+            world: world,
+            orders: orders,
+            objects: objects,
+            bots: bots.iter().map(|bot| (bot.lock().unwrap().id().to_owned(), bot.clone())).collect(),
+            shelves: shelves.iter().map(|shelf| (shelf.lock().unwrap().id().to_owned(), shelf.clone())).collect(),
+            sites: sites.iter().map(|site| (site.lock().unwrap().id().to_owned(), site.clone())).collect()
+        }
+    }
+    pub fn world(&self) -> Arc<Mutex<World>> {
+        self.world.clone()
+    }
+    pub fn orders(&self) -> Arc<Mutex<OrderBook>> {
+        self.orders.clone()
+    }
+    pub fn object(&self, id: &str) -> Result<Arc<Mutex<Object>>, Error> {
+        self.objects.get(id).cloned().ok_or(Error::NotInWorld { id: id.to_owned() })
+    }
+    pub fn bot(&self, id: &str) -> Result<Arc<Mutex<Bot>>, Error> {
+        self.bots.get(id).cloned().ok_or(Error::NotInWorld { id: id.to_owned() })
+    }
+    pub fn shelf(&self, id: &str) -> Result<Arc<Mutex<Shelf>>, Error> {
+        self.shelves.get(id).cloned().ok_or(Error::NotInWorld { id: id.to_owned() })
+    }
+    pub fn site(&self, id: &str) -> Result<Arc<Mutex<Site>>, Error> {
+        self.sites.get(id).cloned().ok_or(Error::NotInWorld { id: id.to_owned() })
+    }
+    pub fn objects(&self) -> &HashMap<String, Arc<Mutex<Object>>> {
+        &self.objects
+    }
+    pub fn begin(&self, commands: &Vec<Box<Command>>) -> Result<Transaction, Error> {
+        let touched = commands.iter()
+            .map(|command| command.objects())
+            .collect::<Option<Vec<Vec<String>>>>()
+            .map(|ids| ids.concat());
+        let orders = match touched.is_none() || commands.iter().any(|command| command.orders().len() > 0) {
+            true => Some(self.orders.lock().unwrap().clone()),
+            false => None
+        };
+        let (world, events, touched) = {
+            let world = self.world.lock().unwrap();
+            // a bot moves the shelf it carries
+            let touched = touched.map(|ids| ids.iter().filter_map(|id| world.carried(id)).chain(ids.iter().cloned()).collect::<HashSet<String>>());
+            (world.backup(&self.objects)?, world.pending_events(), touched)
+        };
+        Ok(Transaction {
+            world: world,
+            orders: orders,
+            bots: backup(&self.bots, &touched),
+            shelves: backup(&self.shelves, &touched),
+            sites: backup(&self.sites, &touched),
+            commands: commands.iter().map(|command| command.snapshot(self)).collect::<Result<Vec<Box<Command>>, Error>>()?,
+            events: events
+        })
+    }
+}
+
+fn copy<T: Clone>(items: &[Arc<Mutex<T>>]) -> Vec<Arc<Mutex<T>>> {
+    items.iter().map(|item| Arc::new(Mutex::new(item.lock().unwrap().clone()))).collect()
+}
+
+pub struct Snapshot {
+    pub world: Arc<Mutex<World>>,
+    pub bots: Vec<Arc<Mutex<Bot>>>,
+    pub shelves: Vec<Arc<Mutex<Shelf>>>,
+    pub sites: Vec<Arc<Mutex<Site>>>,
+    pub scheduler: Arc<Mutex<ParallelCommandQueue>>,
+    pub orders: Arc<Mutex<OrderBook>>
+}
+
+impl Snapshot {
+    pub fn capture(world: &Arc<Mutex<World>>, bots: &[Arc<Mutex<Bot>>], shelves: &[Arc<Mutex<Shelf>>], sites: &[Arc<Mutex<Site>>],
+                   scheduler: &Arc<Mutex<ParallelCommandQueue>>, orders: &Arc<Mutex<OrderBook>>) -> Result<Self, Error> {
+        let (bots, shelves, sites) = (copy(bots), copy(shelves), copy(sites));
+        let orders = Arc::new(Mutex::new(orders.lock().unwrap().clone()));
+        let mut context = SnapshotContext::new(Arc::new(Mutex::new(World::new())), orders.clone(), &bots, &shelves, &sites);
+        context.world = Arc::new(Mutex::new(world.lock().unwrap().fork(context.objects())?));
+        let scheduler = scheduler.lock().unwrap().fork(&context)?;
+        Ok(Self {
+            world: context.world(),
+            bots: bots,
+            shelves: shelves,
+            sites: sites,
+            scheduler: Arc::new(Mutex::new(scheduler)),
+            orders: orders
+        })
+    }
+    pub fn fork(&self) -> Result<Self, Error> {
+        Self::capture(&self.world, &self.bots, &self.shelves, &self.sites, &self.scheduler, &self.orders)
+    }
+    pub fn tick(&self) -> u64 {
+        self.world.lock().unwrap().tick()
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct ObjectRecord {
+    id: String,
+    location: Location,
+    locked: bool,
+    storage: Vec<ItemSet>,
+    capacity: Capacity,
+    #[serde(default)]
+    pick_storage: Option<Vec<ItemSet>>,
+    #[serde(default)]
+    pick_capacity: Option<Capacity>
+}
+
+#[cfg(feature = "serde")]
+impl ObjectRecord {
+    fn capture(object: &Arc<Mutex<Object>>) -> Self {
+        let mut object = object.lock().unwrap();
+        Self {
+            id: object.id().to_owned(),
+            location: *object.location(),
+            locked: *object.get_lock(),
+            storage: object.storage().items().clone(),
+            capacity: object.storage().capacity(),
+            pick_storage: object.pick_storage().map(|storage| storage.items().clone()),
+            pick_capacity: object.pick_storage().map(|storage| storage.capacity())
+        }
+    }
+    fn restore(&self, object: &Arc<Mutex<Object>>) {
+        let mut object = object.lock().unwrap();
+        *object.get_location() = self.location;
+        *object.get_lock() = self.locked;
+        let storage = object.get_storage();
+        storage.set_capacity(self.capacity);
+        storage.items = self.storage.clone();
+    }
+}
+
+#[cfg(feature = "serde")]
+fn invalid<E: ::std::fmt::Display>(err: E) -> Error {
+    Error::Checkpoint(err.to_string())
+}
+
+#[cfg(feature = "serde")]
+fn missing(key: &str) -> Error {
+    Error::Checkpoint(format!("missing field {}", key))
+}
+
+#[cfg(feature = "serde")]
+impl Snapshot {
+    pub fn to_value(&self) -> Result<Value, Error> {
+        let world = self.world.lock().unwrap();
+        let objects = world.items().iter().map(ObjectRecord::capture).collect::<Vec<ObjectRecord>>();
+        let orders = serde_json::to_value(&*self.orders.lock().unwrap()).map_err(invalid)?;
+        Ok(json!({
+            "world": world.checkpoint()?,
+            "objects": objects,
+            "orders": orders,
+            "scheduler": self.scheduler.lock().unwrap().checkpoint()?
+        }))
+    }
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        let records = Vec::<ObjectRecord>::deserialize(value.get("objects").ok_or(missing("objects"))?).map_err(invalid)?;
+        let orders = OrderBook::deserialize(value.get("orders").ok_or(missing("orders"))?).map_err(invalid)?;
+        let (mut bots, mut shelves, mut sites) = (Vec::new(), Vec::new(), Vec::new());
+        let mut items: Vec<Arc<Mutex<Object>>> = Vec::new();
+        for record in &records {
+            let item: Arc<Mutex<Object>> = if record.id.starts_with("bot-") {
+                let bot = Bot::with_id(record.id.clone());
+                bots.push(bot.clone());
+                bot
+            } else if record.id.starts_with("slf-") {
+                let shelf = Shelf::with_id(record.id.clone());
+                shelves.push(shelf.clone());
+                shelf
+            } else if record.id.starts_with("ste-") {
+                let site = Site::with_id(record.id.clone());
+                site.lock().unwrap().pick_storage = Storage::with_capacity(record.pick_capacity.unwrap_or_default());
+                site.lock().unwrap().pick_storage.items = record.pick_storage.clone().unwrap_or(vec![]);
+                sites.push(site.clone());
+                site
+            } else {
+                return Err(Error::Checkpoint(format!("unknown object {}", record.id)));
+            };
+            record.restore(&item);
+            items.push(item);
+        }
+        let world = World::from_checkpoint(value.get("world").ok_or(missing("world"))?, items)?;
+        let orders = Arc::new(Mutex::new(orders));
+        let context = SnapshotContext::new(Arc::new(Mutex::new(world)), orders.clone(), &bots, &shelves, &sites);
+        let scheduler = ParallelCommandQueue::from_checkpoint(value.get("scheduler").ok_or(missing("scheduler"))?, &context)?;
+        Ok(Self {
+            world: context.world(),
+            bots: bots,
+            shelves: shelves,
+            sites: sites,
+            scheduler: Arc::new(Mutex::new(*scheduler)),
+            orders: orders
+        })
+    }
+    pub fn save<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = writer;
+        let value = self.to_value().map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+        serde_json::to_writer(&mut writer, &value)?;
+        writer.flush()
+    }
+    pub fn load<R: Read>(reader: R) -> io::Result<Self> {
+        let value = serde_json::from_reader::<_, Value>(reader)?;
+        Self::from_value(&value).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::command::{ CommandQueue, TestNextCommand, BotMoveCommand };
+    use warehouse::object::Location;
+
+    fn setup() -> (Arc<Mutex<World>>, Vec<Arc<Mutex<Bot>>>, Arc<Mutex<ParallelCommandQueue>>, Arc<Mutex<OrderBook>>) {
+        let world = Arc::new(Mutex::new(World::new()));
+        let bot = Bot::with_id("bot-0001".to_owned());
+        world.lock().unwrap().add_items(vec![bot.clone()]);
+        (world, vec![bot], Arc::new(Mutex::new(ParallelCommandQueue::new())), Arc::new(Mutex::new(OrderBook::new())))
+    }
+
+    #[test]
+    fn test_capture() {
+        let (world, bots, scheduler, orders) = setup();
+        let snapshot = Snapshot::capture(&world, &bots, &[], &[], &scheduler, &orders).unwrap();
+        *bots[0].lock().unwrap().get_location() = Location::new(3, 0, 0);
+        bots[0].lock().unwrap().get_storage().add(1, 1).unwrap();
+        let bot = snapshot.bots[0].lock().unwrap();
+        assert_eq!(*bot.location(), Location::new(0, 0, 0));
+        assert_eq!(bot.storage().units(), 0);
+        assert_eq!(snapshot.world.lock().unwrap().check_location(Location::new(0, 0, 0)), 1);
+    }
+
+    #[test]
+    fn test_rollback_events() {
+        use warehouse::{ EventLog, Event };
+        let (world, bots, _, orders) = setup();
+        let log = EventLog::new();
+        world.lock().unwrap().subscribe(log.clone());
+        let context = SnapshotContext::new(world.clone(), orders, &bots, &[], &[]);
+        bots[0].lock().unwrap().lock().unwrap();
+        let transaction = context.begin(&vec![BotMoveCommand::new(bots[0].clone(), Location::new(1, 0, 0), world.clone()) as Box<Command>]).unwrap();
+        bots[0].lock().unwrap().unlock().unwrap();
+        transaction.rollback(&context);
+        world.lock().unwrap().advance_tick();
+        let events = log.lock().unwrap().events.iter().map(|&(_, ref event)| event.clone()).collect::<Vec<Event>>();
+        assert_eq!(events, vec![Event::ObjectLocked { id: "bot-0001".to_owned() }]);
+        assert!(*bots[0].lock().unwrap().get_lock());
+    }
+
+    #[test]
+    fn test_capture_events() {
+        use warehouse::{ EventLog, Event };
+        let (world, bots, scheduler, orders) = setup();
+        let log = EventLog::new();
+        world.lock().unwrap().subscribe(log.clone());
+        bots[0].lock().unwrap().lock().unwrap();
+        let snapshot = Snapshot::capture(&world, &bots, &[], &[], &scheduler, &orders).unwrap();
+        snapshot.bots[0].lock().unwrap().unlock().unwrap();
+        snapshot.bots[0].lock().unwrap().get_storage().add(1, 1).unwrap();
+        snapshot.world.lock().unwrap().advance_tick();
+        let fork = snapshot.fork().unwrap();
+        fork.bots[0].lock().unwrap().get_storage().add(1, 1).unwrap();
+        fork.world.lock().unwrap().advance_tick();
+        world.lock().unwrap().advance_tick();
+        let events = log.lock().unwrap().events.iter().map(|&(_, ref event)| event.clone()).collect::<Vec<Event>>();
+        assert_eq!(events, vec![Event::ObjectLocked { id: "bot-0001".to_owned() }]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_unknown_object() {
+        let (world, bots, scheduler, orders) = setup();
+        let mut value = Snapshot::capture(&world, &bots, &[], &[], &scheduler, &orders).unwrap().to_value().unwrap();
+        value["objects"][0]["id"] = json!("b\u{e9}\u{e9}-0001");
+        assert_eq!(Snapshot::from_value(&value).err(), Some(Error::Checkpoint("unknown object b\u{e9}\u{e9}-0001".to_owned())));
+    }
+
+    #[test]
+    fn test_begin_touched() {
+        let (world, mut bots, _, orders) = setup();
+        let bot = Bot::with_id("bot-0002".to_owned());
+        world.lock().unwrap().add_items(vec![bot.clone()]);
+        bots.push(bot);
+        let context = SnapshotContext::new(world.clone(), orders, &bots, &[], &[]);
+        let transaction = context.begin(&vec![BotMoveCommand::new(bots[1].clone(), Location::new(1, 0, 0), world.clone()) as Box<Command>]).unwrap();
+        assert_eq!(transaction.bots.iter().map(|&(_, ref bot)| bot.id().to_owned()).collect::<Vec<String>>(), vec!["bot-0002"]);
+        assert!(transaction.orders.is_none());
+        let transaction = context.begin(&vec![Box::new(ParallelCommandQueue::new()) as Box<Command>]).unwrap();
+        assert_eq!(transaction.bots.len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected="command does not support snapshots")]
+    fn test_unsupported() {
+        let (world, bots, scheduler, orders) = setup();
+        scheduler.lock().unwrap().schedule(Box::new(TestNextCommand::new())).unwrap();
+        Snapshot::capture(&world, &bots, &[], &[], &scheduler, &orders).unwrap();
+    }
+}
@@ -1,16 +1,146 @@
+use warehouse::{ ItemCatalog, EventSender, Error };
+use std::sync::Arc;
+#[cfg(feature = "serde")]
+use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+
 pub type Item = u64;
 pub type ItemSet = (Item, u32);
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Capacity {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub skus: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub units: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub weight: Option<u32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub volume: Option<u32>
+}
+
+impl Capacity {
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Clone)]
 pub struct Storage {
-    pub items: Vec<(Item, u32)>
+    pub items: Vec<(Item, u32)>,
+    capacity: Capacity,
+    catalog: Option<Arc<ItemCatalog>>,
+    events: Option<EventSender>
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct ItemEntry {
+    item: Item,
+    count: u32
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Storage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.collect_seq(self.items.iter().map(|&(item, count)| ItemEntry { item: item, count: count }))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Storage {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let entries = Vec::<ItemEntry>::deserialize(deserializer)?;
+        Ok(Self {
+            items: entries.into_iter().map(|entry| (entry.item, entry.count)).collect(),
+            capacity: Capacity::unlimited(),
+            catalog: None,
+            events: None
+        })
+    }
 }
 
 impl Storage {
     pub fn new() -> Self {
+        Self::with_capacity(Capacity::unlimited())
+    }
+    pub fn with_capacity(capacity: Capacity) -> Self {
         Self {
-            items: Vec::new()
+            items: Vec::new(),
+            capacity: capacity,
+            catalog: None,
+            events: None
         }
     }
-    pub fn render(&self) -> Result<String, &'static str> {
+    pub fn capacity(&self) -> Capacity {
+        self.capacity
+    }
+    pub fn set_capacity(&mut self, capacity: Capacity) {
+        self.capacity = capacity;
+    }
+    pub fn set_catalog(&mut self, catalog: Arc<ItemCatalog>) {
+        self.catalog = Some(catalog);
+    }
+    pub fn events(&self) -> Option<&EventSender> {
+        self.events.as_ref()
+    }
+    pub fn set_events(&mut self, events: EventSender) {
+        self.events = Some(events);
+    }
+    fn unit_weight(&self, item: Item) -> u32 {
+        self.catalog.as_ref().map_or(0, |catalog| catalog.weight(item))
+    }
+    fn unit_volume(&self, item: Item) -> u32 {
+        self.catalog.as_ref().map_or(0, |catalog| catalog.volume(item))
+    }
+    pub fn units(&self) -> u32 {
+        self.items.iter().fold(0, |total: u32, set| total.saturating_add(set.1))
+    }
+    pub fn weight(&self) -> u32 {
+        self.items.iter().fold(0, |total: u32, set| total.saturating_add(set.1.saturating_mul(self.unit_weight(set.0))))
+    }
+    pub fn volume(&self) -> u32 {
+        self.items.iter().fold(0, |total: u32, set| total.saturating_add(set.1.saturating_mul(self.unit_volume(set.0))))
+    }
+    pub fn room(&self, item: Item) -> u32 {
+        if let Some(skus) = self.capacity.skus {
+            if self.have(item) == 0 && self.items.len() >= skus {
+                return 0;
+            }
+        }
+        let mut room = u32::max_value() - self.have(item);
+        if let Some(units) = self.capacity.units {
+            room = room.min(units.saturating_sub(self.units()));
+        }
+        if let Some(weight) = self.capacity.weight {
+            let unit_weight = self.unit_weight(item);
+            if unit_weight > 0 {
+                room = room.min(weight.saturating_sub(self.weight()) / unit_weight);
+            }
+        }
+        if let Some(volume) = self.capacity.volume {
+            let unit_volume = self.unit_volume(item);
+            if unit_volume > 0 {
+                room = room.min(volume.saturating_sub(self.volume()) / unit_volume);
+            }
+        }
+        room
+    }
+    pub fn check_add(&self, item: Item, count: u32) -> Result<(), Error> {
+        let room = self.room(item);
+        if count > room {
+            return Err(Error::OverCapacity { item: item, overflow: count - room });
+        }
+        Ok(())
+    }
+    pub fn check_add_all(&self, items: &[ItemSet]) -> Result<(), Error> {
+        let mut storage = self.clone();
+        storage.events = None;
+        for &(item, count) in items {
+            storage.add(item, count)?;
+        }
+        Ok(())
+    }
+    pub fn render(&self) -> Result<String, Error> {
         let result = self.items.iter()
             .map(|set: &ItemSet| format!("{{ \"item\": {}, \"count\": {} }}", set.0, set.1))
             .collect::<Vec<String>>()
@@ -20,7 +150,12 @@ impl Storage {
     pub fn items(&self) -> &Vec<(Item, u32)> {
         &self.items
     }
-    pub fn add(&mut self, item: Item, count: u32) -> Result<(), u32> {
+    pub fn add(&mut self, item: Item, count: u32) -> Result<(), Error> {
+        self.check_add(item, count)?;
+        if let Some(ref events) = self.events {
+            let before = self.have(item);
+            events.storage_changed(item, before, before + count);
+        }
         {
             let mut iter = self.items.iter_mut();
             match iter.find(|&& mut(c_item, _)| c_item == item) {
@@ -35,22 +170,26 @@ impl Storage {
         Ok(())
     }
 
-    pub fn take(&mut self, item: Item, count: u32) -> Result<(), u32> {
+    pub fn take(&mut self, item: Item, count: u32) -> Result<(), Error> {
         let result = {
             let mut iter = self.items.iter_mut();
             match iter.find(|&& mut(c_item, _)| c_item == item) {
                 Some((_, cnt)) => {
                     if *cnt < count {
-                        Err(*cnt)
+                        Err(Error::NotEnoughItem { item: item, have: *cnt, want: count })
                     } else {
                         *cnt -= count;
                         Ok(())
                     }
                 },
-                None => Err(0)
+                None => Err(Error::NotEnoughItem { item: item, have: 0, want: count })
             }
         };
         self.items.retain(|(_, cnt)| *cnt > 0);
+        if let (true, Some(events)) = (result.is_ok(), self.events.as_ref()) {
+            let after = self.have(item);
+            events.storage_changed(item, after + count, after);
+        }
         result
     }
 
@@ -67,7 +206,8 @@ impl Storage {
 
 #[cfg(test)]
 mod tests {
-    use super::Storage;
+    use super::*;
+    use warehouse::ItemInfo;
 
     #[test]
     fn test_render() {
@@ -91,7 +231,7 @@ mod tests {
         assert_eq!(storage.render().unwrap(), "[{ \"item\": 1, \"count\": 2 }]");
     }
     #[test]
-    #[should_panic(expected="3")]
+    #[should_panic(expected="have: 3, want: 4")]
     fn test_take_multiple() {
         let mut storage = Storage::new();
         storage.add(1, 3).unwrap();
@@ -104,6 +244,73 @@ mod tests {
         storage.take(1, 3).unwrap();
         assert_eq!(storage.items.len(), 0);
     }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let mut storage = Storage::new();
+        storage.add(1, 1).unwrap();
+        storage.add(2, 2).unwrap();
+        let value = ::serde_json::to_value(&storage).unwrap();
+        assert_eq!(value, ::serde_json::from_str::<::serde_json::Value>(&storage.render().unwrap()).unwrap());
+        assert_eq!(::serde_json::from_value::<Storage>(value).unwrap().items, vec![(1, 1), (2, 2)]);
+    }
+    #[test]
+    fn test_capacity_units() {
+        let mut storage = Storage::with_capacity(Capacity { units: Some(5), ..Capacity::unlimited() });
+        storage.add(1, 3).unwrap();
+        assert_eq!(storage.room(2), 2);
+        assert_eq!(storage.add(2, 4).unwrap_err(), Error::OverCapacity { item: 2, overflow: 2 });
+        assert_eq!(storage.units(), 3);
+    }
+    #[test]
+    #[should_panic(expected="OverCapacity")]
+    fn test_capacity_skus() {
+        let mut storage = Storage::with_capacity(Capacity { skus: Some(1), ..Capacity::unlimited() });
+        storage.add(1, 3).unwrap();
+        storage.add(1, 3).unwrap();
+        storage.add(2, 1).unwrap();
+    }
+    #[test]
+    fn test_capacity_weight() {
+        let mut catalog = ItemCatalog::new();
+        catalog.insert(ItemInfo { weight: 3, volume: 1, ..ItemInfo::new(1, "box") });
+        catalog.insert(ItemInfo { weight: 1, volume: 4, ..ItemInfo::new(2, "bag") });
+        let mut storage = Storage::with_capacity(Capacity { weight: Some(10), volume: Some(8), ..Capacity::unlimited() });
+        storage.set_catalog(Arc::new(catalog));
+        storage.add(1, 3).unwrap();
+        assert_eq!(storage.weight(), 9);
+        assert_eq!(storage.volume(), 3);
+        assert_eq!(storage.room(1), 0);
+        assert_eq!(storage.room(2), 1);
+        assert_eq!(storage.room(3), u32::max_value());
+    }
+    #[test]
+    fn test_weight_saturates() {
+        let mut catalog = ItemCatalog::new();
+        catalog.insert(ItemInfo { weight: 1000, volume: 1000, ..ItemInfo::new(1, "crate") });
+        let mut storage = Storage::with_capacity(Capacity { weight: Some(100), ..Capacity::unlimited() });
+        storage.add(1, u32::max_value() / 2).unwrap();
+        storage.set_catalog(Arc::new(catalog));
+        assert_eq!(storage.weight(), u32::max_value());
+        assert_eq!(storage.volume(), u32::max_value());
+        assert_eq!(storage.room(1), 0);
+    }
+    #[test]
+    fn test_count_overflow() {
+        let mut storage = Storage::new();
+        storage.add(1, u32::max_value()).unwrap();
+        assert_eq!(storage.add(1, 1).unwrap_err(), Error::OverCapacity { item: 1, overflow: 1 });
+        assert_eq!(storage.have(1), u32::max_value());
+        storage.add(2, 5).unwrap();
+        assert_eq!(storage.units(), u32::max_value());
+    }
+    #[test]
+    fn test_check_add_all() {
+        let storage = Storage::with_capacity(Capacity { units: Some(5), ..Capacity::unlimited() });
+        storage.check_add_all(&[(1, 2), (2, 3)]).unwrap();
+        assert_eq!(storage.check_add_all(&[(1, 2), (2, 4)]).unwrap_err(), Error::OverCapacity { item: 2, overflow: 1 });
+        assert_eq!(storage.units(), 0);
+    }
     #[test]
     fn test_have() {
         let mut storage = Storage::new();
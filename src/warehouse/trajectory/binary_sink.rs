@@ -0,0 +1,239 @@
+use warehouse::trajectory::{ Frame, ObjectState, TrajectorySink };
+use warehouse::object::Location;
+use warehouse::ItemSet;
+use std::io::{ self, Read, Write };
+
+const MAGIC: &'static [u8] = b"WHTR\x01";
+const FRAME: u8 = 1;
+const META: u8 = 2;
+const DELTA: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    Frame(Frame),
+    Meta(String, String)
+}
+
+pub struct BinarySink<W: Write> {
+    writer: W,
+    started: bool
+}
+
+impl<W: Write> BinarySink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: writer,
+            started: false
+        }
+    }
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.writer.write_all(MAGIC)?;
+            self.started = true;
+        }
+        Ok(())
+    }
+    fn write_u32(&mut self, value: u32) -> io::Result<()> {
+        self.writer.write_all(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8])
+    }
+    fn write_u64(&mut self, value: u64) -> io::Result<()> {
+        self.write_u32(value as u32)?;
+        self.write_u32((value >> 32) as u32)
+    }
+    fn write_str(&mut self, value: &str) -> io::Result<()> {
+        self.write_u32(value.len() as u32)?;
+        self.writer.write_all(value.as_bytes())
+    }
+    fn write_items(&mut self, items: &Vec<ItemSet>) -> io::Result<()> {
+        self.write_u32(items.len() as u32)?;
+        for &(item, count) in items {
+            self.write_u64(item)?;
+            self.write_u32(count)?;
+        }
+        Ok(())
+    }
+    fn write_object(&mut self, object: &ObjectState) -> io::Result<()> {
+        self.write_str(&object.id)?;
+        self.write_u32(object.location.x)?;
+        self.write_u32(object.location.y)?;
+        self.write_u32(object.location.z)?;
+        self.writer.write_all(&[object.locked as u8, object.pick_storage.is_some() as u8])?;
+        self.write_items(&object.storage)?;
+        if let Some(ref items) = object.pick_storage {
+            self.write_items(items)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> TrajectorySink for BinarySink<W> {
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        self.start()?;
+        self.writer.write_all(&[if frame.delta { DELTA } else { FRAME }])?;
+        self.write_u64(frame.tick)?;
+        self.write_u32(frame.objects.len() as u32)?;
+        for object in &frame.objects {
+            self.write_object(object)?;
+        }
+        self.write_str(&frame.scheduler)
+    }
+    fn write_meta(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.start()?;
+        self.writer.write_all(&[META])?;
+        self.write_str(key)?;
+        self.write_str(value)
+    }
+    fn finish(&mut self) -> io::Result<()> {
+        self.start()?;
+        self.writer.flush()
+    }
+}
+
+pub struct BinaryReader<R: Read> {
+    reader: R,
+    started: bool
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl<R: Read> BinaryReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: reader,
+            started: false
+        }
+    }
+    fn read_u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0u8; 1];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0u8; 4];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf[0] as u32 | (buf[1] as u32) << 8 | (buf[2] as u32) << 16 | (buf[3] as u32) << 24)
+    }
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let low = self.read_u32()? as u64;
+        let high = self.read_u32()? as u64;
+        Ok(low | high << 32)
+    }
+    fn read_str(&mut self) -> io::Result<String> {
+        let len = self.read_u32()? as usize;
+        let mut buf = vec![0u8; len];
+        self.reader.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|_| invalid("invalid utf-8 string"))
+    }
+    fn read_items(&mut self) -> io::Result<Vec<ItemSet>> {
+        let len = self.read_u32()?;
+        let mut items = Vec::new();
+        for _ in 0..len {
+            let item = self.read_u64()?;
+            items.push((item, self.read_u32()?));
+        }
+        Ok(items)
+    }
+    fn read_object(&mut self) -> io::Result<ObjectState> {
+        let id = self.read_str()?;
+        let location = Location::new(self.read_u32()?, self.read_u32()?, self.read_u32()?);
+        let (locked, has_pick) = (self.read_u8()? != 0, self.read_u8()? != 0);
+        let storage = self.read_items()?;
+        let pick_storage = match has_pick {
+            true => Some(self.read_items()?),
+            false => None
+        };
+        Ok(ObjectState { id: id, location: location, locked: locked, storage: storage, pick_storage: pick_storage })
+    }
+    pub fn next_record(&mut self) -> io::Result<Option<Record>> {
+        if !self.started {
+            let mut magic = [0u8; 5];
+            self.reader.read_exact(&mut magic)?;
+            if &magic[..] != MAGIC {
+                return Err(invalid("not a binary trajectory"));
+            }
+            self.started = true;
+        }
+        let mut tag = [0u8; 1];
+        if self.reader.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+        match tag[0] {
+            FRAME | DELTA => {
+                let tick = self.read_u64()?;
+                let len = self.read_u32()?;
+                let mut objects = Vec::new();
+                for _ in 0..len {
+                    objects.push(self.read_object()?);
+                }
+                Ok(Some(Record::Frame(Frame { tick: tick, objects: objects, scheduler: self.read_str()?, delta: tag[0] == DELTA })))
+            },
+            META => {
+                let key = self.read_str()?;
+                Ok(Some(Record::Meta(key, self.read_str()?)))
+            },
+            _ => Err(invalid("unknown record"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> Frame {
+        Frame {
+            tick: 1 << 40,
+            objects: vec![
+                ObjectState { id: "bot-0001".to_owned(), location: Location::new(1, 2, 3), locked: true, storage: vec![(7, 2)], pick_storage: None },
+                ObjectState { id: "ste-0001".to_owned(), location: Location::new(0, 0, 0), locked: false, storage: vec![], pick_storage: Some(vec![(1, 1)]) }
+            ],
+            scheduler: "{ \"type\": \"test\" }".to_owned(),
+            delta: false
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut data: Vec<u8> = Vec::new();
+        {
+            let mut sink = BinarySink::new(&mut data);
+            sink.write_frame(&frame()).unwrap();
+            sink.write_meta("orders", "[]").unwrap();
+            sink.finish().unwrap();
+        }
+        let mut reader = BinaryReader::new(&data[..]);
+        assert_eq!(reader.next_record().unwrap(), Some(Record::Frame(frame())));
+        assert_eq!(reader.next_record().unwrap(), Some(Record::Meta("orders".to_owned(), "[]".to_owned())));
+        assert_eq!(reader.next_record().unwrap(), None);
+    }
+
+    #[test]
+    fn test_delta() {
+        let mut data: Vec<u8> = Vec::new();
+        let delta = Frame { delta: true, ..frame() };
+        BinarySink::new(&mut data).write_frame(&delta).unwrap();
+        assert_eq!(BinaryReader::new(&data[..]).next_record().unwrap(), Some(Record::Frame(delta)));
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut data: Vec<u8> = Vec::new();
+        BinarySink::new(&mut data).write_frame(&frame()).unwrap();
+        assert!(data.len() < frame().render().len());
+    }
+
+    #[test]
+    #[should_panic(expected="not a binary trajectory")]
+    fn test_bad_magic() {
+        BinaryReader::new(&b"{ \"data\": [] }"[..]).next_record().unwrap();
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut data: Vec<u8> = Vec::new();
+        BinarySink::new(&mut data).finish().unwrap();
+        assert_eq!(BinaryReader::new(&data[..]).next_record().unwrap(), None);
+    }
+}
@@ -0,0 +1,188 @@
+use warehouse::trajectory::{ Frame, ObjectState, TrajectorySink };
+use std::collections::HashMap;
+use std::io;
+
+pub struct DeltaEncoder {
+    interval: u64,
+    frames: u64,
+    last: HashMap<String, ObjectState>
+}
+
+impl DeltaEncoder {
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval: interval,
+            frames: 0,
+            last: HashMap::new()
+        }
+    }
+    pub fn encode(&mut self, frame: &Frame) -> Frame {
+        let keyframe = self.interval <= 1 || self.frames % self.interval == 0;
+        self.frames += 1;
+        let objects = match keyframe {
+            true => frame.objects.clone(),
+            false => frame.objects.iter()
+                .filter(|object| self.last.get(&object.id) != Some(object))
+                .cloned()
+                .collect()
+        };
+        for object in &objects {
+            self.last.insert(object.id.clone(), object.clone());
+        }
+        Frame {
+            tick: frame.tick,
+            objects: objects,
+            scheduler: frame.scheduler.clone(),
+            delta: !keyframe
+        }
+    }
+}
+
+pub struct DeltaDecoder {
+    objects: Vec<ObjectState>,
+    index: HashMap<String, usize>,
+    started: bool
+}
+
+impl DeltaDecoder {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            index: HashMap::new(),
+            started: false
+        }
+    }
+    pub fn apply(&mut self, frame: &Frame) -> io::Result<()> {
+        if !frame.delta {
+            self.objects.clear();
+            self.index.clear();
+            self.started = true;
+        } else if !self.started {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "delta frame before keyframe"));
+        }
+        for object in &frame.objects {
+            match self.index.get(&object.id) {
+                Some(&idx) => self.objects[idx] = object.clone(),
+                None => {
+                    self.index.insert(object.id.clone(), self.objects.len());
+                    self.objects.push(object.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn objects(&self) -> &Vec<ObjectState> {
+        &self.objects
+    }
+    pub fn decode(&mut self, frame: &Frame) -> io::Result<Frame> {
+        self.apply(frame)?;
+        Ok(Frame {
+            tick: frame.tick,
+            objects: self.objects.clone(),
+            scheduler: frame.scheduler.clone(),
+            delta: false
+        })
+    }
+}
+
+pub fn frame_at<I: IntoIterator<Item = Frame>>(frames: I, tick: u64) -> io::Result<Option<Frame>> {
+    let mut decoder = DeltaDecoder::new();
+    for frame in frames {
+        if frame.tick > tick {
+            break;
+        }
+        if frame.tick < tick {
+            decoder.apply(&frame)?;
+            continue;
+        }
+        return decoder.decode(&frame).map(Some);
+    }
+    Ok(None)
+}
+
+pub struct DeltaSink<'a> {
+    sink: Box<TrajectorySink + 'a>,
+    encoder: DeltaEncoder
+}
+
+impl<'a> DeltaSink<'a> {
+    pub fn new(sink: Box<TrajectorySink + 'a>, interval: u64) -> Self {
+        Self {
+            sink: sink,
+            encoder: DeltaEncoder::new(interval)
+        }
+    }
+}
+
+impl<'a> TrajectorySink for DeltaSink<'a> {
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let frame = self.encoder.encode(frame);
+        self.sink.write_frame(&frame)
+    }
+    fn write_meta(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.sink.write_meta(key, value)
+    }
+    fn finish(&mut self) -> io::Result<()> {
+        self.sink.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::object::Location;
+
+    fn object(id: &str, x: u32) -> ObjectState {
+        ObjectState { id: id.to_owned(), location: Location::new(x, 0, 0), locked: false, storage: vec![], pick_storage: None }
+    }
+
+    fn frames() -> Vec<Frame> {
+        (0..5).map(|tick| Frame {
+            tick: tick,
+            objects: vec![object("bot-0001", tick as u32), object("slf-0001", 0), object("slf-0002", 1)],
+            scheduler: "{}".to_owned(),
+            delta: false
+        }).collect()
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut encoder = DeltaEncoder::new(3);
+        let encoded = frames().iter().map(|frame| encoder.encode(frame)).collect::<Vec<Frame>>();
+        assert_eq!(encoded.iter().map(|frame| frame.delta).collect::<Vec<bool>>(), vec![false, true, true, false, true]);
+        assert_eq!(encoded[0].objects.len(), 3);
+        assert_eq!(encoded[1].objects, vec![object("bot-0001", 1)]);
+        assert_eq!(encoded[3].objects.len(), 3);
+    }
+
+    #[test]
+    fn test_encode_full() {
+        let mut encoder = DeltaEncoder::new(1);
+        assert!(frames().iter().all(|frame| encoder.encode(frame) == *frame));
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut encoder = DeltaEncoder::new(3);
+        let mut decoder = DeltaDecoder::new();
+        for frame in frames() {
+            assert_eq!(decoder.decode(&encoder.encode(&frame)).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn test_frame_at() {
+        let mut encoder = DeltaEncoder::new(2);
+        let encoded = frames().iter().map(|frame| encoder.encode(frame)).collect::<Vec<Frame>>();
+        assert_eq!(frame_at(encoded.clone(), 3).unwrap(), Some(frames()[3].clone()));
+        assert_eq!(frame_at(encoded, 10).unwrap(), None);
+    }
+
+    #[test]
+    #[should_panic(expected="delta frame before keyframe")]
+    fn test_missing_keyframe() {
+        let mut encoder = DeltaEncoder::new(3);
+        let encoded = frames().iter().map(|frame| encoder.encode(frame)).collect::<Vec<Frame>>();
+        DeltaDecoder::new().apply(&encoded[1]).unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+use warehouse::object::{ Object, Location };
+use warehouse::{ World, ItemSet, quote };
+use std::sync::{ Arc, Mutex };
+#[cfg(feature = "serde")]
+use serde_json::{ self, Value };
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectState {
+    pub id: String,
+    pub location: Location,
+    pub locked: bool,
+    pub storage: Vec<ItemSet>,
+    pub pick_storage: Option<Vec<ItemSet>>
+}
+
+#[cfg(feature = "serde")]
+fn parse_items(value: &Value) -> Option<Vec<ItemSet>> {
+    value.as_array()?.iter()
+        .map(|set| Some((set.get("item")?.as_u64()?, set.get("count")?.as_u64()? as u32)))
+        .collect()
+}
+
+fn render_items(items: &Vec<ItemSet>) -> String {
+    let result = items.iter()
+        .map(|set: &ItemSet| format!("{{ \"item\": {}, \"count\": {} }}", set.0, set.1))
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!("[{}]", result)
+}
+
+impl ObjectState {
+    pub fn capture(object: &Arc<Mutex<Object>>) -> Self {
+        let mut object = object.lock().unwrap();
+        Self {
+            id: object.id().to_owned(),
+            location: *object.location(),
+            locked: *object.get_lock(),
+            storage: object.storage().items().clone(),
+            pick_storage: object.pick_storage().map(|storage| storage.items().clone())
+        }
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_value(value: &Value) -> Option<Self> {
+        Some(Self {
+            id: value.get("id")?.as_str()?.to_owned(),
+            location: serde_json::from_value(value.get("location")?.clone()).ok()?,
+            locked: value.get("locked")?.as_bool()?,
+            storage: parse_items(value.get("storage")?)?,
+            pick_storage: match value.get("pick_storage") {
+                Some(items) => Some(parse_items(items)?),
+                None => None
+            }
+        })
+    }
+    pub fn render(&self) -> String {
+        let pick_storage = match self.pick_storage {
+            Some(ref items) => format!(", \"pick_storage\": {}", render_items(items)),
+            None => "".to_owned()
+        };
+        format!("{{ \"id\": {}, \"storage\": {}{}, \"location\": {}, \"locked\": {} }}",
+            quote(&self.id), render_items(&self.storage), pick_storage, self.location.render(), self.locked)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub tick: u64,
+    pub objects: Vec<ObjectState>,
+    pub scheduler: String,
+    pub delta: bool
+}
+
+impl Frame {
+    pub fn capture(tick: u64, world: &mut World, scheduler: String) -> Self {
+        Self {
+            tick: tick,
+            objects: world.get_items().iter().map(ObjectState::capture).collect(),
+            scheduler: scheduler,
+            delta: false
+        }
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_value(value: &Value) -> Option<Self> {
+        Some(Self {
+            tick: value.get("tick")?.as_u64()?,
+            objects: value.get("objects")?.as_array()?.iter().map(ObjectState::from_value).collect::<Option<Vec<ObjectState>>>()?,
+            scheduler: value.get("scheduler")?.to_string(),
+            delta: value.get("delta").and_then(Value::as_bool).unwrap_or(false)
+        })
+    }
+    pub fn render(&self) -> String {
+        let objects = self.objects.iter()
+            .map(|object: &ObjectState| object.render())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let delta = match self.delta {
+            true => ", \"delta\": true",
+            false => ""
+        };
+        format!("{{ \"tick\": {}{}, \"objects\": [{}], \"scheduler\": {} }}", self.tick, delta, objects, self.scheduler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::object::{ Bot, Site };
+
+    #[test]
+    fn test_capture() {
+        let mut world = World::new();
+        let (bot, site) = (Bot::with_id("bot-0001".to_owned()), Site::with_id("ste-0001".to_owned()));
+        bot.lock().unwrap().get_storage().add(1, 2).unwrap();
+        bot.lock().unwrap().lock().unwrap();
+        *site.lock().unwrap().get_location() = Location::new(1, 0, 0);
+        world.add_items(vec![bot, site]);
+        let frame = Frame::capture(3, &mut world, "{ \"type\": \"test\" }".to_owned());
+        assert_eq!(frame.objects[0], ObjectState {
+            id: "bot-0001".to_owned(), location: Location::new(0, 0, 0), locked: true, storage: vec![(1, 2)], pick_storage: None
+        });
+        assert_eq!(frame.objects[1].pick_storage, Some(vec![]));
+        assert_eq!(frame.render(), "{ \"tick\": 3, \"objects\": [\
+            { \"id\": \"bot-0001\", \"storage\": [{ \"item\": 1, \"count\": 2 }], \"location\": { \"x\": 0, \"y\": 0, \"z\": 0 }, \"locked\": true }, \
+            { \"id\": \"ste-0001\", \"storage\": [], \"pick_storage\": [], \"location\": { \"x\": 1, \"y\": 0, \"z\": 0 }, \"locked\": false }], \
+            \"scheduler\": { \"type\": \"test\" } }");
+        let delta = Frame { objects: vec![], delta: true, ..frame };
+        assert_eq!(delta.render(), "{ \"tick\": 3, \"delta\": true, \"objects\": [], \"scheduler\": { \"type\": \"test\" } }");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_value() {
+        let mut world = World::new();
+        let (bot, site) = (Bot::with_id("bot-0001".to_owned()), Site::with_id("ste-0001".to_owned()));
+        site.lock().unwrap().pick_storage.add(3, 4).unwrap();
+        world.add_items(vec![bot, site]);
+        let frame = Frame { delta: true, ..Frame::capture(5, &mut world, "{ \"type\": \"test\" }".to_owned()) };
+        let value = serde_json::from_str::<Value>(&frame.render()).unwrap();
+        let loaded = Frame::from_value(&value).unwrap();
+        assert_eq!(loaded.objects, frame.objects);
+        assert_eq!((loaded.tick, loaded.delta), (5, true));
+        assert_eq!(serde_json::from_str::<Value>(&loaded.scheduler).unwrap(), value["scheduler"]);
+        assert!(Frame::from_value(&serde_json::from_str::<Value>("{ \"tick\": 1 }").unwrap()).is_none());
+    }
+}
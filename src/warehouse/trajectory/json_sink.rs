@@ -0,0 +1,132 @@
+use warehouse::trajectory::{ Frame, TrajectorySink };
+use std::io::{ self, Write };
+
+pub struct JsonSink<W: Write> {
+    writer: W,
+    frames: u64,
+    meta: Vec<(String, String)>
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: writer,
+            frames: 0,
+            meta: Vec::new()
+        }
+    }
+}
+
+impl<W: Write> TrajectorySink for JsonSink<W> {
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        match self.frames {
+            0 => self.writer.write_all(b"{ \"data\": [")?,
+            _ => self.writer.write_all(b", ")?
+        }
+        self.writer.write_all(frame.render().as_bytes())?;
+        self.frames += 1;
+        Ok(())
+    }
+    fn write_meta(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.meta.push((key.to_owned(), value.to_owned()));
+        Ok(())
+    }
+    fn finish(&mut self) -> io::Result<()> {
+        if self.frames == 0 {
+            self.writer.write_all(b"{ \"data\": [")?;
+        }
+        self.writer.write_all(b"]")?;
+        for &(ref key, ref value) in &self.meta {
+            write!(self.writer, ", \"{}\": {}", key, value)?;
+        }
+        self.writer.write_all(b" }\n")?;
+        self.writer.flush()
+    }
+}
+
+pub struct JsonLinesSink<W: Write> {
+    writer: W
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: writer
+        }
+    }
+}
+
+impl<W: Write> TrajectorySink for JsonLinesSink<W> {
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        self.writer.write_all(frame.render().as_bytes())?;
+        self.writer.write_all(b"\n")
+    }
+    fn write_meta(&mut self, key: &str, value: &str) -> io::Result<()> {
+        write!(self.writer, "{{ \"{}\": {} }}\n", key, value)
+    }
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(tick: u64) -> Frame {
+        Frame { tick: tick, objects: vec![], scheduler: "{ \"type\": \"test\" }".to_owned(), delta: false }
+    }
+
+    #[test]
+    fn test_json() {
+        let mut data: Vec<u8> = Vec::new();
+        {
+            let mut sink = JsonSink::new(&mut data);
+            sink.write_frame(&frame(0)).unwrap();
+            sink.write_meta("orders", "[]").unwrap();
+            sink.write_frame(&frame(1)).unwrap();
+            sink.finish().unwrap();
+        }
+        assert_eq!(String::from_utf8(data).unwrap(), "{ \"data\": [\
+            { \"tick\": 0, \"objects\": [], \"scheduler\": { \"type\": \"test\" } }, \
+            { \"tick\": 1, \"objects\": [], \"scheduler\": { \"type\": \"test\" } }], \"orders\": [] }\n");
+    }
+
+    #[test]
+    fn test_json_empty() {
+        let mut data: Vec<u8> = Vec::new();
+        JsonSink::new(&mut data).finish().unwrap();
+        assert_eq!(String::from_utf8(data).unwrap(), "{ \"data\": [] }\n");
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let mut data: Vec<u8> = Vec::new();
+        {
+            let mut sink = JsonLinesSink::new(&mut data);
+            sink.write_frame(&frame(0)).unwrap();
+            sink.write_frame(&frame(1)).unwrap();
+            sink.write_meta("orders", "[]").unwrap();
+            sink.finish().unwrap();
+        }
+        let data = String::from_utf8(data).unwrap();
+        let lines = data.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "{ \"tick\": 1, \"objects\": [], \"scheduler\": { \"type\": \"test\" } }");
+        assert_eq!(lines[2], "{ \"orders\": [] }");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_valid_json() {
+        let mut data: Vec<u8> = Vec::new();
+        {
+            let mut sink = JsonSink::new(&mut data);
+            sink.write_frame(&frame(0)).unwrap();
+            sink.write_meta("catalog", "[]").unwrap();
+            sink.finish().unwrap();
+        }
+        let value = ::serde_json::from_slice::<::serde_json::Value>(&data).unwrap();
+        assert_eq!(value["data"][0]["tick"], 0);
+    }
+}
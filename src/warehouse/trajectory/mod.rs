@@ -0,0 +1,13 @@
+mod frame;
+mod sink;
+mod json_sink;
+mod binary_sink;
+mod delta;
+mod reader;
+
+pub use self::frame::{ Frame, ObjectState };
+pub use self::sink::{ TrajectorySink, FORMATS, sink };
+pub use self::json_sink::{ JsonSink, JsonLinesSink };
+pub use self::binary_sink::{ BinarySink, BinaryReader, Record };
+pub use self::delta::{ DeltaDecoder, DeltaSink, frame_at };
+pub use self::reader::read_records;
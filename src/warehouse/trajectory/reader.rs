@@ -0,0 +1,105 @@
+use warehouse::trajectory::{ BinaryReader, Record };
+#[cfg(feature = "serde")]
+use warehouse::trajectory::Frame;
+#[cfg(feature = "serde")]
+use serde_json::{ self, Value };
+use std::io;
+
+#[cfg(feature = "serde")]
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(feature = "serde")]
+fn parse_frame(value: &Value) -> io::Result<Record> {
+    Frame::from_value(value)
+        .map(Record::Frame)
+        .ok_or(invalid(format!("invalid frame {}", value)))
+}
+
+#[cfg(feature = "serde")]
+fn read_json(data: &[u8]) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    if let Ok(Value::Object(document)) = serde_json::from_slice::<Value>(data) {
+        if let Some(frames) = document.get("data").and_then(Value::as_array) {
+            for frame in frames {
+                records.push(parse_frame(frame)?);
+            }
+            for (key, value) in document.iter().filter(|&(key, _)| key != "data") {
+                records.push(Record::Meta(key.clone(), value.to_string()));
+            }
+            return Ok(records);
+        }
+    }
+    for line in String::from_utf8_lossy(data).lines().filter(|line| !line.trim().is_empty()) {
+        let value = serde_json::from_str::<Value>(line)?;
+        if value.get("tick").is_some() {
+            records.push(parse_frame(&value)?);
+            continue;
+        }
+        match value.as_object() {
+            Some(meta) if meta.len() == 1 => for (key, value) in meta {
+                records.push(Record::Meta(key.clone(), value.to_string()));
+            },
+            _ => return Err(invalid(format!("invalid record {}", line)))
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(not(feature = "serde"))]
+fn read_json(_: &[u8]) -> io::Result<Vec<Record>> {
+    Err(io::Error::new(io::ErrorKind::Other, "JSON trajectories require the serde feature"))
+}
+
+pub fn read_records(data: &[u8]) -> io::Result<Vec<Record>> {
+    if !data.starts_with(b"WHTR") {
+        return read_json(data);
+    }
+    let mut reader = BinaryReader::new(data);
+    let mut records = Vec::new();
+    while let Some(record) = reader.next_record()? {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::trajectory::{ Frame, TrajectorySink, FORMATS, sink };
+
+    fn records(format: &str) -> Vec<Record> {
+        let mut data: Vec<u8> = Vec::new();
+        {
+            let mut sink = sink(format, &mut data).unwrap();
+            sink.write_frame(&Frame { tick: 0, objects: vec![], scheduler: "{}".to_owned(), delta: false }).unwrap();
+            sink.write_frame(&Frame { tick: 1, objects: vec![], scheduler: "{}".to_owned(), delta: true }).unwrap();
+            sink.write_meta("orders", "[]").unwrap();
+            sink.finish().unwrap();
+        }
+        read_records(&data).unwrap()
+    }
+
+    #[test]
+    fn test_binary() {
+        let records = records("binary");
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2], Record::Meta("orders".to_owned(), "[]".to_owned()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_formats() {
+        for format in FORMATS {
+            assert_eq!(records(format), records("binary"));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    #[should_panic(expected="invalid record")]
+    fn test_invalid() {
+        read_records(b"{ \"a\": 1, \"b\": 2 }\n").unwrap();
+    }
+}
@@ -0,0 +1,38 @@
+use warehouse::trajectory::{ Frame, JsonSink, JsonLinesSink, BinarySink };
+use std::io::{ self, Write };
+
+pub const FORMATS: &'static [&'static str] = &["json", "jsonl", "binary"];
+
+pub trait TrajectorySink {
+    fn write_frame(&mut self, frame: &Frame) -> io::Result<()>;
+    fn write_meta(&mut self, key: &str, value: &str) -> io::Result<()>;
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+pub fn sink<'a, W: Write + 'a>(format: &str, writer: W) -> Option<Box<TrajectorySink + 'a>> {
+    match format {
+        "json" => Some(Box::new(JsonSink::new(writer))),
+        "jsonl" => Some(Box::new(JsonLinesSink::new(writer))),
+        "binary" => Some(Box::new(BinarySink::new(writer))),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sink() {
+        for format in FORMATS {
+            let mut data: Vec<u8> = Vec::new();
+            {
+                let mut sink = sink(format, &mut data).unwrap();
+                sink.write_frame(&Frame { tick: 0, objects: vec![], scheduler: "{}".to_owned(), delta: false }).unwrap();
+                sink.finish().unwrap();
+            }
+            assert!(data.len() > 0);
+        }
+        assert!(sink("xml", Vec::new()).is_none());
+    }
+}
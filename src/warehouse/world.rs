@@ -1,55 +1,533 @@
-use warehouse::object::{ Object, Location };
+use warehouse::object::{ Object, Location, Direction };
+use warehouse::{ ReservationTable, IdGenerator, ItemCatalog, Metrics, EventBus, EventSender, Event, Observer, GridMap, Error };
 use std::sync::{ Arc, Mutex };
-use std::collections::HashMap;
+use std::collections::{ BTreeMap, HashMap, HashSet, VecDeque };
+#[cfg(feature = "serde")]
+use warehouse::object::{ Bot, Shelf, Site };
+#[cfg(feature = "serde")]
+use warehouse::Cell;
+#[cfg(feature = "serde")]
+use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+#[cfg(feature = "serde")]
+use serde::{ de, ser };
+#[cfg(feature = "serde")]
+use serde_json::{ self, Value };
+
+pub const RESERVATION_HORIZON: u64 = 64;
+pub const WAIT_LIMIT: u32 = 32;
 
 pub struct World {
     items: Vec<Arc<Mutex<Object>>>,
-    is_available: HashMap<Location, u64>
+    occupants: HashMap<Location, Vec<String>>,
+    bound: Location,
+    tick: u64,
+    reservations: ReservationTable,
+    ids: IdGenerator,
+    catalog: Arc<ItemCatalog>,
+    metrics: Metrics,
+    events: Arc<Mutex<EventBus>>,
+    carried: HashMap<String, Arc<Mutex<Object>>>,
+    pods: HashMap<String, Arc<Mutex<Object>>>,
+    elevators: HashMap<(u32, u32), u64>,
+    grid: Arc<GridMap>
 }
 
 impl World {
     pub fn new() -> Self {
+        Self::with_ids(IdGenerator::sequential())
+    }
+    pub fn with_ids(ids: IdGenerator) -> Self {
         Self {
             items: Vec::new(),
-            is_available: HashMap::new()
+            occupants: HashMap::new(),
+            bound: Location::new(0, 0, 0),
+            tick: 0,
+            reservations: ReservationTable::new(),
+            ids: ids,
+            catalog: Arc::new(ItemCatalog::new()),
+            metrics: Metrics::new(),
+            events: EventBus::new(),
+            carried: HashMap::new(),
+            pods: HashMap::new(),
+            elevators: HashMap::new(),
+            grid: Arc::new(GridMap::new())
+        }
+    }
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+    pub fn get_metrics(&mut self) -> &mut Metrics {
+        &mut self.metrics
+    }
+    pub fn subscribe(&mut self, observer: Arc<Mutex<Observer>>) {
+        self.events.lock().unwrap().subscribe(observer);
+    }
+    pub fn emit(&mut self, event: Event) {
+        self.events.lock().unwrap().emit(event);
+    }
+    pub fn pending_events(&self) -> usize {
+        self.events.lock().unwrap().pending()
+    }
+    pub fn discard_events(&mut self, from: usize) {
+        self.events.lock().unwrap().discard(from);
+    }
+    pub fn catalog(&self) -> &ItemCatalog {
+        &self.catalog
+    }
+    pub fn set_catalog(&mut self, catalog: Arc<ItemCatalog>) {
+        for item in &self.items {
+            item.lock().unwrap().get_storage().set_catalog(catalog.clone());
         }
+        self.catalog = catalog;
+    }
+    pub fn next_id(&mut self, prefix: &str) -> String {
+        self.ids.next(prefix)
     }
     pub fn add_items(&mut self, items: Vec<Arc<Mutex<Object>>>) {
         let mut items = items;
-        for item in &items {
-            let item = item.lock().unwrap();
-            self.is_available.entry(*item.location()).or_insert(0);
-            (*self.is_available.get_mut(item.location()).unwrap()) += 1;
+        for object in &items {
+            let mut item = object.lock().unwrap();
+            if item.drive_under() {
+                self.pods.insert(item.id().to_owned(), object.clone());
+            }
+            self.bind(&mut *item);
+            self.ids.observe(item.id());
+            self.enter(item.id(), *item.location());
+            self.extend_bound(*item.location());
         }
         self.items.append(&mut items);
     }
+    fn bind(&self, item: &mut Object) {
+        let events = EventSender::new(item.id(), self.events.clone());
+        item.get_storage().set_catalog(self.catalog.clone());
+        item.get_storage().set_events(events.clone());
+        if let Some(storage) = item.get_pick_storage() {
+            storage.set_events(events.for_pick_storage());
+        }
+    }
+    pub fn items(&self) -> &Vec<Arc<Mutex<Object>>> {
+        &self.items
+    }
     pub fn get_items(&mut self) -> &mut Vec<Arc<Mutex<Object>>> {
         &mut self.items
     }
+    fn enter(&mut self, id: &str, location: Location) {
+        self.occupants.entry(location).or_insert_with(Vec::new).push(id.to_owned());
+    }
+    fn leave(&mut self, id: &str, location: Location) -> bool {
+        let left = match self.occupants.get_mut(&location) {
+            Some(ids) => match ids.iter().position(|occupant| occupant == id) {
+                Some(idx) => {
+                    ids.remove(idx);
+                    true
+                },
+                None => false
+            },
+            None => false
+        };
+        if self.occupants.get(&location).map_or(false, Vec::is_empty) {
+            self.occupants.remove(&location);
+        }
+        left
+    }
     pub fn check_location(&self, location: Location) -> u64 {
-        match self.is_available.get(&location) {
-            Some(cnt) => *cnt,
+        match self.occupants.get(&location) {
+            Some(ids) => ids.len() as u64,
+            None => 0
+        }
+    }
+    pub fn check_obstacle(&self, location: Location) -> u64 {
+        let moving = match self.reservations.owner(location, self.tick) {
+            Some(_) => 1,
             None => 0
+        };
+        self.check_location(location).saturating_sub(moving)
+    }
+    pub fn check_passage(&self, id: &str, location: Location) -> u64 {
+        let obstacles = self.check_obstacle(location);
+        if obstacles == 0 || self.carried.contains_key(id) {
+            return obstacles;
+        }
+        let pods = self.occupants.get(&location).map_or(0, |ids| ids.iter().filter(|occupant| self.free_pod(occupant)).count());
+        obstacles.saturating_sub(pods as u64)
+    }
+    fn free_pod(&self, id: &str) -> bool {
+        match self.pods.get(id).map(|pod| pod.try_lock()) {
+            Some(Ok(mut pod)) => !*pod.get_lock(),
+            _ => false
+        }
+    }
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+    pub fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+        self.reservations.expire(tick);
+    }
+    pub fn advance_tick(&mut self) {
+        self.events.lock().unwrap().flush(self.tick);
+        self.tick += 1;
+        self.reservations.expire(self.tick);
+    }
+    pub fn reservations(&self) -> &ReservationTable {
+        &self.reservations
+    }
+    pub fn reserve_path(&mut self, id: &str, from: Location, path: &[Location]) -> Result<(), Error> {
+        let mut cells = vec![from];
+        cells.extend_from_slice(path);
+        let tick = self.tick;
+        self.reservations.reserve(id, &cells, tick)
+    }
+    pub fn reserve_edge(&mut self, id: &str, from: Location, to: Location) -> Result<(), Error> {
+        self.reservations.reserve_edge(id, from, to)
+    }
+    pub fn release(&mut self, id: &str) {
+        self.reservations.release(id);
+    }
+    pub fn occupant(&self, location: Location) -> Option<String> {
+        let moving = self.reservations.owner(location, self.tick);
+        self.occupants.get(&location).and_then(|ids| {
+            ids.iter().find(|id| Some(id.as_str()) != moving).or(ids.first()).cloned()
+        })
+    }
+    fn reserved_error(&self, location: Location, tick: u64) -> Error {
+        Error::LocationReserved {
+            location: location,
+            tick: tick,
+            by: self.reservations.owner(location, tick).unwrap_or("unknown").to_owned()
+        }
+    }
+    pub fn check_goal(&self, id: &str, location: Location) -> Result<(), Error> {
+        if self.check_passage(id, location) > 0 {
+            return Err(Error::LocationOccupied {
+                location: location,
+                by: self.occupant(location).unwrap_or("unknown".to_owned())
+            });
+        }
+        self.grid.check(location)?;
+        match self.grid.can_stop(location) {
+            true => Ok(()),
+            false => Err(Error::NoStop { location: location })
+        }
+    }
+    pub fn find_departure(&self, id: &str, from: Location, to: Location) -> Result<u64, Error> {
+        if self.check_passage(id, to) > 0 {
+            return Err(Error::LocationOccupied {
+                location: to,
+                by: self.occupant(to).unwrap_or("unknown".to_owned())
+            });
+        }
+        let mut first_err = None;
+        for depart in self.tick..self.tick + RESERVATION_HORIZON {
+            if !self.reservations.is_free(id, from, depart) {
+                if first_err.is_none() {
+                    first_err = Some(self.reserved_error(from, depart));
+                }
+                break;
+            }
+            let result = self.reservations.check_move(id, from, to, depart).and_then(|_| {
+                match self.reservations.free_from(id, to) {
+                    Some(free) if free <= depart + 1 => Ok(()),
+                    Some(free) => Err(self.reserved_error(to, free - 1)),
+                    None => Err(self.reserved_error(to, u64::max_value()))
+                }
+            });
+            match result {
+                Ok(_) => return Ok(depart),
+                Err(err) => if first_err.is_none() {
+                    first_err = Some(err);
+                }
+            }
         }
+        Err(first_err.unwrap_or(self.reserved_error(to, self.tick + RESERVATION_HORIZON)))
     }
-    pub fn notify_will_move(&mut self, obj: Arc<Mutex<Object>>, to_location: Location) -> Result<(), &'static str> {
+    pub fn notify_will_move(&mut self, obj: Arc<Mutex<Object>>, to_location: Location) -> Result<(), Error> {
         let obj = obj.lock().unwrap();
         let from_location = *obj.location();
-        self.is_available.entry(to_location).or_insert(0);
-        match self.is_available.get_mut(&from_location) {
-            Some(cnt) => {
-                if *cnt <= 0 {
-                    return Err("not enough item");
-                }
-                *cnt -= 1;
-            },
-            None => return Err("not enough item")
+        if !self.leave(obj.id(), from_location) {
+            return Err(Error::NotInWorld { id: obj.id().to_owned() });
+        }
+        self.enter(obj.id(), to_location);
+        self.extend_bound(to_location);
+        self.metrics.record_move(obj.id(), from_location, to_location);
+        self.emit(Event::ObjectMoved { id: obj.id().to_owned(), from: from_location, to: to_location });
+        if let Some(shelf) = self.carried.get(obj.id()).cloned() {
+            let mut shelf = shelf.lock().unwrap();
+            let (id, from) = (shelf.id().to_owned(), *shelf.location());
+            self.leave(&id, from);
+            self.enter(&id, to_location);
+            *shelf.get_location() = to_location;
+            self.emit(Event::ObjectMoved { id: id, from: from, to: to_location });
         }
-        (*self.is_available.get_mut(&to_location).unwrap()) += 1;
         Ok(())
     }
-    pub fn render(&self) -> Result<String, &'static str> {
-        let mut error_flag: Option<&'static str> = None;
+    pub fn lift(&mut self, id: &str, shelf: Arc<Mutex<Object>>) -> Result<(), Error> {
+        if let Some(carried) = self.carried(id) {
+            return Err(Error::AlreadyCarrying { id: id.to_owned(), shelf: carried });
+        }
+        self.carried.insert(id.to_owned(), shelf);
+        Ok(())
+    }
+    pub fn lower(&mut self, id: &str) -> Result<Arc<Mutex<Object>>, Error> {
+        self.carried.remove(id).ok_or(Error::NotCarrying { id: id.to_owned() })
+    }
+    pub fn carried(&self, id: &str) -> Option<String> {
+        self.carried.get(id).map(|shelf| shelf.lock().unwrap().id().to_owned())
+    }
+    pub fn add_elevator(&mut self, x: u32, y: u32, ticks: u64) {
+        self.elevators.insert((x, y), ticks.max(1));
+        self.extend_bound(Location::new(x, y, 0));
+    }
+    pub fn elevators(&self) -> Vec<(u32, u32, u64)> {
+        let mut elevators = self.elevators.iter()
+            .map(|(&(x, y), &ticks)| (x, y, ticks))
+            .collect::<Vec<(u32, u32, u64)>>();
+        elevators.sort();
+        elevators
+    }
+    pub fn elevator(&self, location: Location) -> Option<u64> {
+        self.elevators.get(&(location.x, location.y)).cloned()
+    }
+    pub fn check_climb(&self, from: Location, to: Location) -> Result<u64, Error> {
+        if from.z == to.z {
+            return Ok(1);
+        }
+        match from.direction_to(to) {
+            Some(direction) if direction.is_vertical() => self.elevator(from).ok_or(Error::NoElevator { location: from }),
+            _ => Err(Error::NoElevator { location: from })
+        }
+    }
+    pub fn grid(&self) -> &GridMap {
+        &self.grid
+    }
+    pub fn get_grid(&mut self) -> &mut GridMap {
+        Arc::make_mut(&mut self.grid)
+    }
+    pub fn set_grid(&mut self, grid: GridMap) {
+        self.grid = Arc::new(grid);
+    }
+    pub fn check_step(&self, from: Location, to: Location) -> Result<u64, Error> {
+        let ticks = self.grid.check_step(from, to)?;
+        Ok(ticks.max(self.check_climb(from, to)?))
+    }
+    pub fn travel_distances(&self, from: Location) -> HashMap<Location, u32> {
+        let limit = Location::new(self.bound.x.max(from.x) + 1, self.bound.y.max(from.y) + 1, self.bound.z.max(from.z) + 1);
+        let mut distances: HashMap<Location, u32> = HashMap::new();
+        let mut queue: BTreeMap<u32, Vec<Location>> = BTreeMap::new();
+        queue.insert(0, vec![from]);
+        while let Some(distance) = queue.keys().next().cloned() {
+            for current in queue.remove(&distance).unwrap_or_default() {
+                if distances.contains_key(&current) {
+                    continue;
+                }
+                distances.insert(current, distance);
+                for (_, next) in self.neighbours(current, limit) {
+                    if distances.contains_key(&next) || self.check_obstacle(next) > 0 {
+                        continue;
+                    }
+                    if let Ok(ticks) = self.check_step(current, next) {
+                        queue.entry(distance + ticks as u32).or_insert_with(Vec::new).push(next);
+                    }
+                }
+            }
+        }
+        distances
+    }
+    fn extend_bound(&mut self, location: Location) {
+        self.bound = Location::new(
+            self.bound.x.max(location.x),
+            self.bound.y.max(location.y),
+            self.bound.z.max(location.z));
+    }
+    fn neighbours(&self, location: Location, limit: Location) -> Vec<(Direction, Location)> {
+        location.neighbors()
+            .filter(|&(_, next)| next.x <= limit.x && next.y <= limit.y && next.z <= limit.z)
+            .collect()
+    }
+    pub fn free_neighbours(&self, location: Location) -> Vec<Location> {
+        let limit = Location::new(self.bound.x + 1, self.bound.y + 1, self.bound.z + 1);
+        self.neighbours(location, limit).into_iter()
+            .map(|(_, next)| next)
+            .filter(|next| self.check_location(*next) == 0)
+            .filter(|next| self.grid.check(*next).is_ok() && self.grid.can_stop(*next))
+            .collect()
+    }
+    pub fn find_path(&self, id: &str, from: Location, to: Location) -> Option<Vec<Location>> {
+        self.search(id, from, to)
+    }
+    fn search(&self, id: &str, from: Location, to: Location) -> Option<Vec<Location>> {
+        if from != to && self.check_goal(id, to).is_err() {
+            return None;
+        }
+        let free_from = match self.reservations.free_from(id, to) {
+            Some(tick) => tick,
+            None => return None
+        };
+        let limit = Location::new(
+            self.bound.x.max(to.x).max(from.x) + 1,
+            self.bound.y.max(to.y).max(from.y) + 1,
+            self.bound.z.max(to.z).max(from.z) + 1);
+        let climb = self.elevators.values().cloned().max().unwrap_or(1);
+        let horizon = self.tick.max(free_from) + RESERVATION_HORIZON + (limit.x + limit.y) as u64 + limit.z as u64 * climb;
+        let mut previous: HashMap<(Location, u64), (Location, u64)> = HashMap::new();
+        let mut visited: HashSet<(Location, u64)> = HashSet::new();
+        let mut queue: BTreeMap<u64, VecDeque<Location>> = BTreeMap::new();
+        visited.insert((from, self.tick));
+        queue.entry(self.tick).or_insert_with(VecDeque::new).push_back(from);
+        while let Some(tick) = queue.keys().next().cloned() {
+            let current = match queue.get_mut(&tick).and_then(VecDeque::pop_front) {
+                Some(current) => current,
+                None => {
+                    queue.remove(&tick);
+                    continue;
+                }
+            };
+            if current == to && tick >= free_from {
+                let mut path = Vec::new();
+                let mut state = (current, tick);
+                while state != (from, self.tick) {
+                    path.push(state.0);
+                    let before = previous[&state];
+                    for _ in before.1 + 1..state.1 {
+                        path.push(before.0);
+                    }
+                    state = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if tick >= horizon {
+                continue;
+            }
+            let mut candidates = self.neighbours(current, limit).into_iter()
+                .map(|(_, next)| next)
+                .collect::<Vec<Location>>();
+            candidates.push(current);
+            for next in candidates {
+                let arrive = match self.check_step(current, next) {
+                    Ok(ticks) => tick + ticks,
+                    Err(_) => continue
+                };
+                if arrive > tick + 1 && !self.grid.can_stop(current) {
+                    continue;
+                }
+                if visited.contains(&(next, arrive)) {
+                    continue;
+                }
+                if next != from && self.check_passage(id, next) > 0 {
+                    continue;
+                }
+                if (tick..arrive - 1).any(|wait| self.reservations.check_move(id, current, current, wait).is_err()) {
+                    continue;
+                }
+                if self.reservations.check_move(id, current, next, arrive - 1).is_err() {
+                    continue;
+                }
+                visited.insert((next, arrive));
+                previous.insert((next, arrive), (current, tick));
+                queue.entry(arrive).or_insert_with(VecDeque::new).push_back(next);
+            }
+        }
+        None
+    }
+    pub fn fork(&self, objects: &HashMap<String, Arc<Mutex<Object>>>) -> Result<World, Error> {
+        let mut world = self.backup(objects)?;
+        world.events = EventBus::new();
+        for item in &world.items {
+            world.bind(&mut *item.lock().unwrap());
+        }
+        Ok(world)
+    }
+    // Unlike fork, the backup keeps emitting to this world's event bus.
+    pub fn backup(&self, objects: &HashMap<String, Arc<Mutex<Object>>>) -> Result<World, Error> {
+        let items = self.items.iter()
+            .map(|item| {
+                let id = item.lock().unwrap().id().to_owned();
+                objects.get(&id).cloned().ok_or(Error::NotInWorld { id: id })
+            })
+            .collect::<Result<Vec<Arc<Mutex<Object>>>, Error>>()?;
+        let carried = self.carried.iter()
+            .map(|(id, shelf)| {
+                let shelf = shelf.lock().unwrap().id().to_owned();
+                objects.get(&shelf).cloned().map(|shelf| (id.clone(), shelf)).ok_or(Error::NotInWorld { id: shelf })
+            })
+            .collect::<Result<HashMap<String, Arc<Mutex<Object>>>, Error>>()?;
+        let pods = self.pods.keys()
+            .map(|id| objects.get(id).cloned().map(|pod| (id.clone(), pod)).ok_or(Error::NotInWorld { id: id.clone() }))
+            .collect::<Result<HashMap<String, Arc<Mutex<Object>>>, Error>>()?;
+        Ok(World {
+            items: items,
+            occupants: self.occupants.clone(),
+            bound: self.bound,
+            tick: self.tick,
+            reservations: self.reservations.clone(),
+            ids: self.ids.clone(),
+            catalog: self.catalog.clone(),
+            metrics: self.metrics.clone(),
+            events: self.events.clone(),
+            carried: carried,
+            pods: pods,
+            elevators: self.elevators.clone(),
+            grid: self.grid.clone()
+        })
+    }
+    #[cfg(feature = "serde")]
+    pub fn checkpoint(&self) -> Result<Value, Error> {
+        Ok(json!({
+            "tick": self.tick,
+            "bound": self.bound,
+            "reservations": serde_json::to_value(&self.reservations).map_err(|err| Error::Checkpoint(err.to_string()))?,
+            "ids": serde_json::to_value(&self.ids).map_err(|err| Error::Checkpoint(err.to_string()))?,
+            "catalog": serde_json::to_value(&*self.catalog).map_err(|err| Error::Checkpoint(err.to_string()))?,
+            "metrics": serde_json::to_value(&self.metrics).map_err(|err| Error::Checkpoint(err.to_string()))?,
+            "carried": self.carried.iter()
+                .map(|(id, shelf)| (id.clone(), shelf.lock().unwrap().id().to_owned()))
+                .collect::<HashMap<String, String>>(),
+            "elevators": self.elevators(),
+            "grid": { "size": self.grid.size(), "cells": self.grid.cells() }
+        }))
+    }
+    #[cfg(feature = "serde")]
+    pub fn from_checkpoint(value: &Value, items: Vec<Arc<Mutex<Object>>>) -> Result<World, Error> {
+        fn field<'de, T: Deserialize<'de>>(value: &'de Value, key: &str) -> Result<T, Error> {
+            value.get(key)
+                .ok_or(Error::Checkpoint(format!("missing field {}", key)))
+                .and_then(|value| T::deserialize(value).map_err(|err| Error::Checkpoint(format!("{}: {}", key, err))))
+        }
+        let mut world = World::with_ids(field(value, "ids")?);
+        world.set_catalog(Arc::new(field(value, "catalog")?));
+        world.add_items(items);
+        world.extend_bound(field(value, "bound")?);
+        world.tick = field(value, "tick")?;
+        world.reservations = field(value, "reservations")?;
+        world.metrics = field(value, "metrics")?;
+        if value.get("carried").is_some() {
+            for (id, shelf) in field::<HashMap<String, String>>(value, "carried")? {
+                let shelf = world.items.iter()
+                    .find(|item| item.lock().unwrap().id() == shelf)
+                    .cloned()
+                    .ok_or(Error::NotInWorld { id: shelf })?;
+                world.carried.insert(id, shelf);
+            }
+        }
+        if value.get("elevators").is_some() {
+            for (x, y, ticks) in field::<Vec<(u32, u32, u64)>>(value, "elevators")? {
+                world.add_elevator(x, y, ticks);
+            }
+        }
+        if let Some(grid) = value.get("grid") {
+            world.set_grid(match field::<Option<Location>>(grid, "size")? {
+                Some(size) => GridMap::with_size(size),
+                None => GridMap::new()
+            });
+            for (location, cell) in field::<Vec<(Location, Cell)>>(grid, "cells")? {
+                *world.get_grid().get_cell(location) = cell;
+            }
+        }
+        Ok(world)
+    }
+    pub fn render(&self) -> Result<String, Error> {
+        let mut error_flag: Option<Error> = None;
         let result = self.items.iter()
             .map(|object: &Arc<Mutex<Object>>| match object.lock().unwrap().render() {
                 Ok(result) => result,
@@ -67,6 +545,40 @@ impl World {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for World {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let items = self.items.iter()
+            .map(|item| item.lock().unwrap().to_value())
+            .collect::<Result<Vec<::serde_json::Value>, Error>>()
+            .map_err(ser::Error::custom)?;
+        serializer.collect_seq(items)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for World {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let mut items: Vec<Arc<Mutex<Object>>> = Vec::new();
+        for value in Vec::<Value>::deserialize(deserializer)? {
+            let id = value.get("id").and_then(Value::as_str).unwrap_or("").to_owned();
+            let item: Arc<Mutex<Object>> = if id.starts_with("bot-") {
+                Arc::new(Mutex::new(serde_json::from_value::<Bot>(value).map_err(de::Error::custom)?))
+            } else if id.starts_with("slf-") {
+                Arc::new(Mutex::new(serde_json::from_value::<Shelf>(value).map_err(de::Error::custom)?))
+            } else if id.starts_with("ste-") {
+                Arc::new(Mutex::new(serde_json::from_value::<Site>(value).map_err(de::Error::custom)?))
+            } else {
+                return Err(de::Error::custom(Error::Checkpoint(format!("unknown object {}", id))));
+            };
+            items.push(item);
+        }
+        let mut world = World::new();
+        world.add_items(items);
+        Ok(world)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,7 +615,181 @@ mod tests {
         let bot = Bot::new();
         world.add_items(vec![bot.clone() as Arc<Mutex<Object>>]);
         world.notify_will_move(bot, Location::new(1, 1, 1)).unwrap();
-        assert_eq!(*world.is_available.get(&Location::new(1, 1, 1)).unwrap(), 1);
+        assert_eq!(world.check_location(Location::new(1, 1, 1)), 1);
+    }
+    #[test]
+    fn test_occupant() {
+        use warehouse::object::Shelf;
+        let mut world = World::new();
+        let (bot, shelf) = (Bot::new(), Shelf::new());
+        world.add_items(vec![bot.clone() as Arc<Mutex<Object>>, shelf.clone()]);
+        let (bot, shelf) = (bot.lock().unwrap(), shelf.lock().unwrap());
+        world.reserve_path(bot.id(), Location::new(0, 0, 0), &vec![Location::new(0, 0, 0)]).unwrap();
+        assert_eq!(world.occupant(Location::new(0, 0, 0)), Some(shelf.id().to_owned()));
+        assert_eq!(world.occupant(Location::new(1, 0, 0)), None);
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_checkpoint_error() {
+        assert_eq!(World::from_checkpoint(&json!({}), vec![]).err(), Some(Error::Checkpoint("missing field ids".to_owned())));
+        let err = World::from_checkpoint(&json!({ "ids": "seq" }), vec![]).err().unwrap();
+        assert!(err.to_string().starts_with("invalid checkpoint: ids: "));
+    }
+    #[test]
+    fn test_find_path() {
+        let mut world = World::new();
+        let bot = Bot::new();
+        world.add_items(vec![bot.clone() as Arc<Mutex<Object>>]);
+        let path = world.find_path("bot-1", Location::new(0, 0, 0), Location::new(2, 1, 0)).unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(path[2], Location::new(2, 1, 0));
+        assert_eq!(world.find_path("bot-1", Location::new(0, 0, 0), Location::new(0, 0, 0)).unwrap(), vec![]);
+    }
+    #[test]
+    fn test_find_path_obstacle() {
+        let mut world = World::new();
+        let (bot, obstacle) = (Bot::new(), Bot::new());
+        *obstacle.lock().unwrap().get_location() = Location::new(1, 0, 0);
+        world.add_items(vec![bot.clone() as Arc<Mutex<Object>>, obstacle.clone() as Arc<Mutex<Object>>]);
+        let path = world.find_path("bot-1", Location::new(0, 0, 0), Location::new(2, 0, 0)).unwrap();
+        assert_eq!(path.len(), 4);
+        assert!(!path.contains(&Location::new(1, 0, 0)));
+        assert!(world.find_path("bot-1", Location::new(0, 0, 0), Location::new(1, 0, 0)).is_none());
+    }
+    #[test]
+    fn test_find_path_reserved() {
+        let mut world = World::new();
+        world.reserve_path("bot-2", Location::new(1, 1, 0), &vec![Location::new(1, 0, 0), Location::new(1, 0, 1)]).unwrap();
+        let path = world.find_path("bot-1", Location::new(0, 0, 0), Location::new(2, 0, 0)).unwrap();
+        for (idx, location) in path.iter().enumerate() {
+            assert!(world.reservations().is_free("bot-1", *location, idx as u64 + 1));
+        }
+        assert!(world.find_path("bot-1", Location::new(0, 0, 0), Location::new(1, 0, 1)).is_none());
+    }
+    #[test]
+    fn test_find_path_wait_for_goal() {
+        let mut world = World::new();
+        world.reserve_path("bot-2", Location::new(1, 1, 0), &vec![Location::new(1, 0, 0), Location::new(2, 0, 0), Location::new(3, 0, 0)]).unwrap();
+        let path = world.find_path("bot-1", Location::new(0, 0, 0), Location::new(2, 0, 0)).unwrap();
+        assert!(path.len() >= 3);
+        assert_eq!(path[path.len() - 1], Location::new(2, 0, 0));
+    }
+    #[test]
+    fn test_next_id() {
+        let mut world = World::new();
+        world.add_items(vec![Bot::with_id("bot-0007".to_owned())]);
+        assert_eq!(world.next_id("bot"), "bot-0008");
+        assert_eq!(world.next_id("slf"), "slf-0001");
+    }
+
+    #[test]
+    fn test_free_neighbours() {
+        let mut world = World::new();
+        let bot = Bot::new();
+        *bot.lock().unwrap().get_location() = Location::new(1, 0, 0);
+        world.add_items(vec![bot]);
+        assert_eq!(world.free_neighbours(Location::new(0, 0, 0)), vec![Location::new(0, 1, 0), Location::new(0, 0, 1)]);
+    }
+
+    #[test]
+    fn test_notify_move_metrics() {
+        let mut world = World::new();
+        let bot = Bot::with_id("bot-0001".to_owned());
+        world.add_items(vec![bot.clone()]);
+        world.notify_will_move(bot.clone(), Location::new(0, 1, 0)).unwrap();
+        assert_eq!(world.metrics().bot("bot-0001").unwrap().distance, 1);
+    }
+
+    #[test]
+    fn test_object_events() {
+        use warehouse::object::Site;
+        use warehouse::EventLog;
+        let mut world = World::new();
+        let (bot, site) = (Bot::with_id("bot-0001".to_owned()), Site::new());
+        world.add_items(vec![bot.clone() as Arc<Mutex<Object>>, site.clone()]);
+        let log = EventLog::new();
+        world.subscribe(log.clone());
+        bot.lock().unwrap().lock().unwrap();
+        world.notify_will_move(bot.clone(), Location::new(1, 0, 0)).unwrap();
+        bot.lock().unwrap().unlock().unwrap();
+        let id = {
+            let mut site = site.lock().unwrap();
+            site.get_pick_storage().unwrap().add(3, 2).unwrap();
+            site.id().to_owned()
+        };
+        world.advance_tick();
+        let events = log.lock().unwrap().events.iter().map(|&(_, ref event)| event.clone()).collect::<Vec<Event>>();
+        assert_eq!(events, vec![
+            Event::ObjectLocked { id: "bot-0001".to_owned() },
+            Event::ObjectMoved { id: "bot-0001".to_owned(), from: Location::new(0, 0, 0), to: Location::new(1, 0, 0) },
+            Event::ObjectUnlocked { id: "bot-0001".to_owned() },
+            Event::StorageChanged { id: id, item: 3, before: 0, after: 2, pick_storage: true }
+        ]);
+    }
+
+    #[test]
+    fn test_advance_tick() {
+        let mut world = World::new();
+        world.reserve_path("bot-1", Location::new(0, 0, 0), &vec![Location::new(1, 0, 0)]).unwrap();
+        world.advance_tick();
+        assert_eq!(world.tick(), 1);
+        assert_eq!(world.reservations().owner(Location::new(0, 0, 0), 0), None);
+        assert_eq!(world.reservations().owner(Location::new(1, 0, 0), 1), Some("bot-1"));
+    }
+    #[test]
+    fn test_check_obstacle() {
+        let mut world = World::new();
+        let bot = Bot::new();
+        world.add_items(vec![bot.clone() as Arc<Mutex<Object>>]);
+        assert_eq!(world.check_obstacle(Location::new(0, 0, 0)), 1);
+        world.reserve_path("bot-1", Location::new(0, 0, 0), &vec![Location::new(1, 0, 0)]).unwrap();
+        assert_eq!(world.check_obstacle(Location::new(0, 0, 0)), 0);
+    }
+    #[test]
+    fn test_find_departure() {
+        let mut world = World::new();
+        world.reserve_path("bot-2", Location::new(0, 0, 0), &vec![Location::new(1, 0, 0), Location::new(2, 0, 0)]).unwrap();
+        assert_eq!(world.find_departure("bot-1", Location::new(1, 1, 0), Location::new(1, 0, 0)), Ok(1));
+        match world.find_departure("bot-1", Location::new(2, 1, 0), Location::new(2, 0, 0)) {
+            Err(Error::LocationReserved { location, by, .. }) => {
+                assert_eq!(location, Location::new(2, 0, 0));
+                assert_eq!(by, "bot-2");
+            },
+            _ => panic!("expected reservation conflict")
+        }
+        assert_eq!(world.find_departure("bot-1", Location::new(1, 0, 0), Location::new(0, 0, 0)),
+            Err(Error::SwapConflict { location: Location::new(0, 0, 0), by: "bot-2".to_owned() }));
+    }
+    #[test]
+    fn test_elevator() {
+        let mut world = World::new();
+        world.add_elevator(2, 0, 3);
+        assert!(world.find_path("bot-1", Location::new(0, 0, 0), Location::new(0, 0, 1)).unwrap().contains(&Location::new(2, 0, 1)));
+        assert_eq!(world.check_climb(Location::new(0, 0, 0), Location::new(0, 0, 1)), Err(Error::NoElevator { location: Location::new(0, 0, 0) }));
+        assert_eq!(world.check_climb(Location::new(2, 0, 0), Location::new(2, 0, 1)), Ok(3));
+        assert_eq!(world.travel_distances(Location::new(0, 0, 0)).get(&Location::new(0, 0, 1)), Some(&7));
+        assert_eq!(World::new().travel_distances(Location::new(0, 0, 0)).get(&Location::new(0, 0, 1)), None);
+    }
+    #[test]
+    fn test_find_path_grid() {
+        let mut world = World::new();
+        let mut grid = GridMap::with_size(Location::new(3, 2, 1));
+        grid.add_wall(Location::new(1, 0, 0));
+        grid.set_lane(Location::new(1, 1, 0), Direction::West);
+        world.set_grid(grid.clone());
+        assert!(world.find_path("bot-1", Location::new(0, 0, 0), Location::new(2, 0, 0)).is_none());
+        let path = world.find_path("bot-1", Location::new(2, 0, 0), Location::new(0, 0, 0)).unwrap();
+        assert_eq!(path, vec![Location::new(2, 1, 0), Location::new(1, 1, 0), Location::new(0, 1, 0), Location::new(0, 0, 0)]);
+        assert!(world.find_path("bot-1", Location::new(0, 0, 0), Location::new(3, 0, 0)).is_none());
+        grid.add_no_stop(Location::new(0, 0, 0));
+        world.set_grid(grid);
+        assert!(world.find_path("bot-1", Location::new(2, 0, 0), Location::new(0, 0, 0)).is_none());
+        assert_eq!(world.free_neighbours(Location::new(0, 1, 0)), vec![Location::new(1, 1, 0)]);
+        let distances = world.travel_distances(Location::new(0, 0, 0));
+        assert_eq!(distances.get(&Location::new(2, 1, 0)), None);
+        assert_eq!(world.travel_distances(Location::new(2, 0, 0)).get(&Location::new(0, 1, 0)), Some(&3));
+        #[cfg(feature = "serde")]
+        assert_eq!(World::from_checkpoint(&world.checkpoint().unwrap(), vec![]).unwrap().grid(), world.grid());
     }
     #[test]
     fn test_render() {
@@ -112,4 +798,50 @@ mod tests {
         world.add_items(vec![bot.clone() as Arc<Mutex<Object>>]);
         assert_eq!(world.render().unwrap(), format!("[{{ \"id\": \"{}\", \"storage\": [], \"location\": {{ \"x\": 0, \"y\": 0, \"z\": 0 }} }}]", bot.lock().unwrap().id()));
     }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use warehouse::object::{ Shelf, Site };
+        let mut world = World::new();
+        let (bot, shelf, site) = (Bot::new(), Shelf::new(), Site::new());
+        *shelf.lock().unwrap().get_location() = Location::new(1, 0, 0);
+        *site.lock().unwrap().get_location() = Location::new(2, 0, 0);
+        site.lock().unwrap().pick_storage.add(1, 1).unwrap();
+        world.add_items(vec![bot.clone() as Arc<Mutex<Object>>, shelf.clone(), site.clone()]);
+        let value = serde_json::to_value(&world).unwrap();
+        assert_eq!(value, serde_json::from_str::<Value>(&world.render().unwrap()).unwrap());
+        let mut loaded = serde_json::from_value::<World>(value.clone()).unwrap();
+        assert_eq!(loaded.get_items().len(), 3);
+        assert_eq!(loaded.check_location(Location::new(2, 0, 0)), 1);
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), value);
+    }
+    #[test]
+    fn test_carried() {
+        use warehouse::object::Shelf;
+        let mut world = World::new();
+        let (bot, shelf) = (Bot::new(), Shelf::new());
+        world.add_items(vec![bot.clone() as Arc<Mutex<Object>>, shelf.clone()]);
+        let id = bot.lock().unwrap().id().to_owned();
+        world.lift(&id, shelf.clone()).unwrap();
+        assert_eq!(world.lift(&id, shelf.clone()), Err(Error::AlreadyCarrying { id: id.clone(), shelf: shelf.lock().unwrap().id().to_owned() }));
+        world.notify_will_move(bot.clone(), Location::new(0, 1, 0)).unwrap();
+        assert_eq!(*shelf.lock().unwrap().location(), Location::new(0, 1, 0));
+        assert_eq!(world.check_location(Location::new(0, 0, 0)), 0);
+        assert_eq!(world.check_location(Location::new(0, 1, 0)), 2);
+        let objects = world.items().iter().map(|item| (item.lock().unwrap().id().to_owned(), item.clone())).collect();
+        assert_eq!(world.fork(&objects).unwrap().carried(&id), world.carried(&id));
+        #[cfg(feature = "serde")]
+        {
+            let loaded = World::from_checkpoint(&world.checkpoint().unwrap(), world.items().clone()).unwrap();
+            assert_eq!(loaded.carried(&id), world.carried(&id));
+        }
+        world.lower(&id).unwrap();
+        assert_eq!(world.lower(&id).err(), Some(Error::NotCarrying { id: id }));
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    #[should_panic(expected="unknown object")]
+    fn test_deserialize_unknown() {
+        serde_json::from_str::<World>("[{ \"id\": \"b\u{e9}\u{e9}-1\", \"storage\": [], \"location\": { \"x\": 0, \"y\": 0, \"z\": 0 } }]").unwrap();
+    }
 }
@@ -0,0 +1,337 @@
+use warehouse::{ World, Storage, Capacity, ItemCatalog, IdGenerator, GridMap, OrderBook };
+use warehouse::object::{ Bot, Shelf, Site, Location, Direction, Object };
+use worlddata::util::{ move_location, map_as_object, WorldData };
+use serde_json;
+use std::fs::File;
+use std::io::{ self, Read };
+use std::path::Path;
+use std::sync::{ Arc, Mutex };
+
+#[derive(Deserialize)]
+pub struct ShelfBlock {
+    pub origin: Location,
+    pub width: u32,
+    pub depth: u32,
+    pub height: u32,
+    #[serde(default = "Storage::new")]
+    pub stock: Storage,
+    #[serde(default)]
+    pub capacity: Capacity
+}
+
+#[derive(Deserialize)]
+pub struct SiteSpec {
+    pub location: Location,
+    #[serde(default = "Storage::new")]
+    pub stock: Storage,
+    #[serde(default)]
+    pub capacity: Capacity
+}
+
+#[derive(Deserialize)]
+pub struct BotSpec {
+    pub location: Location,
+    #[serde(default)]
+    pub capacity: Capacity
+}
+
+#[derive(Deserialize)]
+pub struct ElevatorSpec {
+    pub x: u32,
+    pub y: u32,
+    #[serde(default = "default_elevator_ticks")]
+    pub ticks: u64
+}
+
+fn default_elevator_ticks() -> u64 {
+    1
+}
+
+#[derive(Deserialize)]
+pub struct LaneSpec {
+    pub location: Location,
+    pub lane: Direction
+}
+
+#[derive(Deserialize)]
+pub struct ZoneSpec {
+    pub location: Location,
+    pub ticks: u64
+}
+
+#[derive(Deserialize)]
+pub struct OrderSpec {
+    pub lines: Storage,
+    #[serde(default)]
+    pub priority: u32,
+    pub due: u64
+}
+
+#[derive(Deserialize)]
+pub struct Layout {
+    pub size: Location,
+    #[serde(default)]
+    pub items: ItemCatalog,
+    #[serde(default)]
+    pub shelves: Vec<ShelfBlock>,
+    #[serde(default)]
+    pub sites: Vec<SiteSpec>,
+    #[serde(default)]
+    pub bots: Vec<BotSpec>,
+    #[serde(default)]
+    pub elevators: Vec<ElevatorSpec>,
+    #[serde(default)]
+    pub walls: Vec<Location>,
+    #[serde(default)]
+    pub lanes: Vec<LaneSpec>,
+    #[serde(default)]
+    pub zones: Vec<ZoneSpec>,
+    #[serde(default)]
+    pub no_stop: Vec<Location>,
+    #[serde(default)]
+    pub orders: Vec<OrderSpec>
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn fill(obj: Arc<Mutex<Object>>, stock: &Storage, capacity: Capacity, catalog: &Arc<ItemCatalog>) -> io::Result<()> {
+    let mut obj = obj.lock().unwrap();
+    obj.get_storage().set_capacity(capacity);
+    obj.get_storage().set_catalog(catalog.clone());
+    for &(item, count) in stock.items() {
+        obj.get_storage().add(item, count).map_err(|err| invalid(format!("{}", err)))?;
+    }
+    Ok(())
+}
+
+impl Layout {
+    pub fn from_reader<R: Read>(reader: R) -> io::Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+    fn check(&self, location: Location) -> io::Result<Location> {
+        if location.x >= self.size.x || location.y >= self.size.y || location.z >= self.size.z {
+            return Err(invalid(format!("location {} out of grid {}", location, self.size)));
+        }
+        Ok(location)
+    }
+    pub fn build(&self) -> io::Result<WorldData> {
+        self.build_with_ids(IdGenerator::sequential())
+    }
+    pub fn build_with_ids(&self, ids: IdGenerator) -> io::Result<WorldData> {
+        let mut world = World::with_ids(ids);
+        let catalog = Arc::new(self.items.clone());
+        world.set_catalog(catalog.clone());
+        let mut vec_shelf: Vec<Arc<Mutex<Shelf>>> = Vec::new();
+        let mut vec_site: Vec<Arc<Mutex<Site>>> = Vec::new();
+        let mut vec_bot: Vec<Arc<Mutex<Bot>>> = Vec::new();
+        for block in &self.shelves {
+            for col in 0..block.width {
+                for row in 0..block.depth {
+                    for tal in 0..block.height {
+                        let location = self.check(Location::new(block.origin.x + col, block.origin.y + row, block.origin.z + tal))?;
+                        let shelf = Shelf::with_id(world.next_id("slf"));
+                        move_location(shelf.clone(), location);
+                        fill(shelf.clone(), &block.stock, block.capacity, &catalog)?;
+                        vec_shelf.push(shelf);
+                    }
+                }
+            }
+        }
+        for spec in &self.sites {
+            let site = Site::with_id(world.next_id("ste"));
+            move_location(site.clone(), self.check(spec.location)?);
+            fill(site.clone(), &spec.stock, spec.capacity, &catalog)?;
+            vec_site.push(site);
+        }
+        for spec in &self.bots {
+            let bot = Bot::with_id(world.next_id("bot"));
+            move_location(bot.clone(), self.check(spec.location)?);
+            fill(bot.clone(), &Storage::new(), spec.capacity, &catalog)?;
+            vec_bot.push(bot);
+        }
+        let mut grid = GridMap::with_size(self.size);
+        for &wall in &self.walls {
+            grid.add_wall(self.check(wall)?);
+        }
+        for spec in &self.lanes {
+            grid.set_lane(self.check(spec.location)?, spec.lane);
+        }
+        for spec in &self.zones {
+            grid.set_zone(self.check(spec.location)?, spec.ticks);
+        }
+        for &location in &self.no_stop {
+            grid.add_no_stop(self.check(location)?);
+        }
+        world.set_grid(grid);
+        for spec in &self.elevators {
+            self.check(Location::new(spec.x, spec.y, 0))?;
+            world.add_elevator(spec.x, spec.y, spec.ticks);
+        }
+        world.add_items(map_as_object(&vec_shelf));
+        world.add_items(map_as_object(&vec_site));
+        world.add_items(map_as_object(&vec_bot));
+        Ok((world, vec_bot, vec_shelf, vec_site))
+    }
+    pub fn build_orders(&self) -> io::Result<OrderBook> {
+        let mut orders = OrderBook::new();
+        for spec in &self.orders {
+            if spec.lines.items().len() == 0 {
+                return Err(invalid("order without lines".to_owned()));
+            }
+            orders.add(spec.lines.items().clone(), spec.priority, spec.due).map_err(|err| invalid(format!("{}", err)))?;
+        }
+        Ok(orders)
+    }
+}
+
+pub fn load_layout<P: AsRef<Path>>(path: P, ids: IdGenerator) -> io::Result<(WorldData, OrderBook)> {
+    let layout = Layout::load(path)?;
+    Ok((layout.build_with_ids(ids)?, layout.build_orders()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use worlddata::one_bot;
+
+    const ONE_BOT: &'static str = include_str!("../../layouts/one_bot.json");
+
+    #[test]
+    fn test_one_bot_layout() {
+        let (world, bots, shelves, sites) = Layout::from_reader(ONE_BOT.as_bytes()).unwrap().build().unwrap();
+        let (expected_world, expected_bots, expected_shelves, expected_sites) = one_bot();
+        assert_eq!(bots.len(), expected_bots.len());
+        assert_eq!(shelves.len(), expected_shelves.len());
+        assert_eq!(sites.len(), expected_sites.len());
+        for (shelf, expected) in shelves.iter().zip(expected_shelves.iter()) {
+            assert_eq!(*shelf.lock().unwrap().location(), *expected.lock().unwrap().location());
+        }
+        for (site, expected) in sites.iter().zip(expected_sites.iter()) {
+            assert_eq!(*site.lock().unwrap().location(), *expected.lock().unwrap().location());
+        }
+        assert_eq!(*bots[0].lock().unwrap().location(), *expected_bots[0].lock().unwrap().location());
+        assert_eq!(bots[0].lock().unwrap().id(), expected_bots[0].lock().unwrap().id());
+        assert_eq!(world.elevators(), expected_world.elevators());
+        assert_eq!(world.grid(), expected_world.grid());
+    }
+
+    #[test]
+    fn test_stock() {
+        let layout = "{ \"size\": { \"x\": 4, \"y\": 4, \"z\": 2 },
+            \"shelves\": [{ \"origin\": { \"x\": 1, \"y\": 1, \"z\": 0 }, \"width\": 1, \"depth\": 2, \"height\": 2,
+                \"stock\": [{ \"item\": 1, \"count\": 5 }] }],
+            \"sites\": [{ \"location\": { \"x\": 3, \"y\": 3, \"z\": 0 }, \"stock\": [{ \"item\": 2, \"count\": 1 }] }],
+            \"bots\": [{ \"location\": { \"x\": 0, \"y\": 0, \"z\": 0 } }, { \"location\": { \"x\": 0, \"y\": 1, \"z\": 0 } }] }";
+        let (mut world, bots, shelves, sites) = Layout::from_reader(layout.as_bytes()).unwrap().build().unwrap();
+        assert_eq!(bots.len(), 2);
+        assert_eq!(shelves.len(), 4);
+        assert_eq!(world.get_items().len(), 7);
+        assert_eq!(world.check_location(Location::new(1, 2, 1)), 1);
+        assert!(shelves.iter().all(|shelf| shelf.lock().unwrap().storage().have(1) == 5));
+        assert_eq!(sites[0].lock().unwrap().storage().have(2), 1);
+    }
+
+    #[test]
+    fn test_grid() {
+        let layout = "{ \"size\": { \"x\": 4, \"y\": 4, \"z\": 1 },
+            \"walls\": [{ \"x\": 1, \"y\": 1, \"z\": 0 }],
+            \"lanes\": [{ \"location\": { \"x\": 0, \"y\": 2, \"z\": 0 }, \"lane\": \"south\" }],
+            \"zones\": [{ \"location\": { \"x\": 2, \"y\": 2, \"z\": 0 }, \"ticks\": 3 }],
+            \"no_stop\": [{ \"x\": 3, \"y\": 3, \"z\": 0 }] }";
+        let (world, _, _, _) = Layout::from_reader(layout.as_bytes()).unwrap().build().unwrap();
+        let grid = world.grid();
+        assert_eq!(grid.size(), Some(Location::new(4, 4, 1)));
+        assert!(grid.cell(Location::new(1, 1, 0)).wall);
+        assert_eq!(grid.cell(Location::new(0, 2, 0)).lane, Some(Direction::South));
+        assert_eq!(grid.cell(Location::new(2, 2, 0)).ticks, 3);
+        assert!(!grid.can_stop(Location::new(3, 3, 0)));
+    }
+
+    #[test]
+    fn test_orders() {
+        let layout = "{ \"size\": { \"x\": 4, \"y\": 4, \"z\": 1 },
+            \"orders\": [{ \"lines\": [{ \"item\": 1, \"count\": 2 }, { \"item\": 3, \"count\": 1 }], \"priority\": 2, \"due\": 50 },
+                { \"lines\": [{ \"item\": 2, \"count\": 1 }], \"due\": 10 }] }";
+        let orders = Layout::from_reader(layout.as_bytes()).unwrap().build_orders().unwrap();
+        assert_eq!(orders.orders().len(), 2);
+        assert_eq!(orders.orders()[0].lines, vec![(1, 2), (3, 1)]);
+        assert_eq!(orders.orders()[0].priority, 2);
+        assert_eq!(orders.orders()[0].due, 50);
+        assert_eq!(orders.orders()[1].priority, 0);
+        assert_eq!(Layout::from_reader(ONE_BOT.as_bytes()).unwrap().build_orders().unwrap().orders().len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected="order without lines")]
+    fn test_empty_order() {
+        let layout = "{ \"size\": { \"x\": 4, \"y\": 4, \"z\": 1 }, \"orders\": [{ \"lines\": [], \"due\": 10 }] }";
+        Layout::from_reader(layout.as_bytes()).unwrap().build_orders().unwrap();
+    }
+
+    #[test]
+    fn test_order_lines() {
+        let layout = "{ \"size\": { \"x\": 4, \"y\": 4, \"z\": 1 },
+            \"orders\": [{ \"lines\": [{ \"item\": 1, \"count\": 2 }, { \"item\": 1, \"count\": 1 }], \"due\": 10 }] }";
+        let orders = Layout::from_reader(layout.as_bytes()).unwrap().build_orders().unwrap();
+        assert_eq!(orders.orders()[0].lines, vec![(1, 3)]);
+        let layout = "{ \"size\": { \"x\": 4, \"y\": 4, \"z\": 1 }, \"orders\": [{ \"lines\": [{ \"item\": 1, \"count\": 0 }], \"due\": 10 }] }";
+        let err = Layout::from_reader(layout.as_bytes()).unwrap().build_orders().err().unwrap();
+        assert_eq!(format!("{}", err), "invalid order line for item 1: count 0");
+    }
+
+    #[test]
+    fn test_seeded_ids() {
+        let layout = Layout::from_reader(ONE_BOT.as_bytes()).unwrap();
+        let (_, bots1, _, _) = layout.build_with_ids(IdGenerator::seeded(7)).unwrap();
+        let (_, bots2, _, _) = layout.build_with_ids(IdGenerator::seeded(7)).unwrap();
+        assert_eq!(bots1[0].lock().unwrap().id(), bots2[0].lock().unwrap().id());
+        assert_ne!(bots1[0].lock().unwrap().id(), "bot-0001");
+    }
+
+    #[test]
+    fn test_capacity() {
+        let layout = "{ \"size\": { \"x\": 4, \"y\": 4, \"z\": 1 },
+            \"sites\": [{ \"location\": { \"x\": 3, \"y\": 3, \"z\": 0 }, \"capacity\": { \"units\": 10 } }],
+            \"bots\": [{ \"location\": { \"x\": 0, \"y\": 0, \"z\": 0 }, \"capacity\": { \"skus\": 1, \"units\": 4 } }] }";
+        let (_, bots, _, sites) = Layout::from_reader(layout.as_bytes()).unwrap().build().unwrap();
+        assert_eq!(bots[0].lock().unwrap().storage().capacity(), Capacity { skus: Some(1), units: Some(4), weight: None, volume: None });
+        assert_eq!(sites[0].lock().unwrap().storage().capacity().units, Some(10));
+    }
+
+    #[test]
+    fn test_catalog() {
+        let layout = "{ \"size\": { \"x\": 4, \"y\": 4, \"z\": 1 },
+            \"items\": [{ \"id\": 1, \"name\": \"crate\", \"weight\": 5 }],
+            \"bots\": [{ \"location\": { \"x\": 0, \"y\": 0, \"z\": 0 }, \"capacity\": { \"weight\": 12 } }] }";
+        let (world, bots, _, _) = Layout::from_reader(layout.as_bytes()).unwrap().build().unwrap();
+        assert_eq!(world.catalog().name(1), Some("crate"));
+        assert_eq!(bots[0].lock().unwrap().storage().room(1), 2);
+    }
+
+    #[test]
+    #[should_panic(expected="over capacity")]
+    fn test_stock_over_capacity() {
+        let layout = "{ \"size\": { \"x\": 4, \"y\": 4, \"z\": 1 },
+            \"sites\": [{ \"location\": { \"x\": 3, \"y\": 3, \"z\": 0 }, \"capacity\": { \"units\": 1 },
+                \"stock\": [{ \"item\": 2, \"count\": 2 }] }] }";
+        Layout::from_reader(layout.as_bytes()).unwrap().build().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected="out of grid")]
+    fn test_out_of_grid() {
+        let layout = "{ \"size\": { \"x\": 2, \"y\": 2, \"z\": 1 }, \"bots\": [{ \"location\": { \"x\": 2, \"y\": 0, \"z\": 0 } }] }";
+        Layout::from_reader(layout.as_bytes()).unwrap().build().unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_json() {
+        Layout::from_reader("{ \"size\": 1 }".as_bytes()).unwrap();
+    }
+}
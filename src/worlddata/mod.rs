@@ -1,8 +1,16 @@
 mod one_bot;
 mod one_runner;
+mod scenario;
+mod replay;
+#[cfg(feature = "serde")]
+mod layout;
 
 pub mod util;
 
-pub use self::one_bot::one_bot;
+pub use self::one_bot::{ one_bot, one_bot_with_ids };
 pub use self::one_runner::OneRunner;
 pub use self::one_runner::Runner;
+pub use self::scenario::{ scenario, SCENARIOS };
+pub use self::replay::{ load_replay, unlock_all };
+#[cfg(feature = "serde")]
+pub use self::layout::{ Layout, load_layout };
@@ -1,34 +1,41 @@
-use warehouse::World;
+use warehouse::{ World, IdGenerator, GridMap };
 use warehouse::object::{ Bot, Shelf, Site, Location };
-use worlddata::util::{ move_location, map_as_object };
+use worlddata::util::{ move_location, map_as_object, WorldData };
 use std::sync::{ Arc, Mutex };
 
-pub fn one_bot() -> (World, 
-    Vec<Arc<Mutex<Bot>>>,
-    Vec<Arc<Mutex<Shelf>>>,
-    Vec<Arc<Mutex<Site>>>) {
+const ELEVATOR_TICKS: u64 = 2;
+
+pub fn one_bot() -> WorldData {
+    one_bot_with_ids(IdGenerator::sequential())
+}
+
+pub fn one_bot_with_ids(ids: IdGenerator) -> WorldData {
     let (SHELF_COUNT, SHELF_WIDTH, SHELF_HEIGHT) = (6, 10, 5);
     let (MARGIN_L, MARGIN_T) = (1, 1);
-    let mut world = World::new();
+    let mut world = World::with_ids(ids);
+    world.set_grid(GridMap::with_size(Location::new(SHELF_COUNT * 3 + MARGIN_L + 1, MARGIN_T + SHELF_WIDTH + 4, SHELF_HEIGHT + 1)));
     let mut vec_shelf : Vec<Arc<Mutex<Shelf>>> = Vec::new();
     let mut vec_site : Vec<Arc<Mutex<Site>>> = Vec::new();
     for cnt in 0..SHELF_COUNT {
         for col in 0..2 {
             for row in 0..SHELF_WIDTH {
                 for tal in 0..SHELF_HEIGHT {
-                    let mut shelf = Shelf::new();
+                    let mut shelf = Shelf::with_id(world.next_id("slf"));
                     move_location(shelf.clone(), Location::new(cnt * 3 + col + MARGIN_L, MARGIN_T + row, tal));
                     vec_shelf.push(shelf);
                 }
             }
         }
-        let mut site = Site::new();
+        let mut site = Site::with_id(world.next_id("ste"));
         move_location(site.clone(), Location::new(cnt * 3 + MARGIN_L + 1, MARGIN_T + SHELF_WIDTH + 1, 0));
         vec_site.push(site);
     }
+    for cnt in 0..SHELF_COUNT + 1 {
+        world.add_elevator(cnt * 3 + MARGIN_L - 1, MARGIN_T - 1, ELEVATOR_TICKS);
+    }
     world.add_items(map_as_object(&vec_shelf));
     world.add_items(map_as_object(&vec_site));
-    let bot = Bot::new();
+    let bot = Bot::with_id(world.next_id("bot"));
     move_location(bot.clone(), Location::new(MARGIN_L, SHELF_WIDTH + 3, 0));
     world.add_items(vec![bot.clone()]);
     (world, vec![bot], vec_shelf, vec_site)
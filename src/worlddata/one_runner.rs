@@ -1,39 +1,46 @@
-use warehouse::World;
-use warehouse::object::{ Bot, Shelf, Site, Location, Object };
-use warehouse::command::{ Command, ParallelCommandQueue, BotMoveCommand, CommandQueue };
-use std;
+use warehouse::{ World, OrderBook, Snapshot, SnapshotContext, Event, Error };
+use warehouse::object::{ Bot, Shelf, Site, Object };
+use warehouse::command::{ Command, Failure, FailurePolicy, ParallelCommandQueue, SequentialCommandQueue, SitePickCommand, CommandQueue };
+use warehouse::dispatcher::{ Dispatcher, DispatchContext, GreedyDispatcher };
 use std::sync::{ Arc, Mutex };
-use std::io::Write;
-use std::fs::File;
+use warehouse::trajectory::{ Frame, TrajectorySink };
+use std::io;
 use super::one_bot;
+use super::util::WorldData;
 
 pub trait Runner {
     fn scheduler(&self) -> Arc<Mutex<Command>>;
     fn world(&self) -> Arc<Mutex<World>>;
-    fn task(&mut self) -> Result<(), &'static str>;
-    fn tick(&mut self) -> Result<String, &'static str> {
+    fn orders(&self) -> Arc<Mutex<OrderBook>>;
+    fn task(&mut self) -> Result<(), Error>;
+    fn idle(&self) -> bool;
+    fn snapshot(&self) -> Result<Snapshot, Error>;
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), Error>;
+    fn sample(&mut self) {}
+    fn consume(&mut self) -> Result<(), Error> {
+        self.scheduler().lock().unwrap().consume()?;
+        Ok(())
+    }
+    fn tick(&mut self) -> Result<String, Error> {
         { self.task()?; }
-        let scheduler = self.scheduler();
-        let mut scheduler = scheduler.lock().unwrap();
-        let data = scheduler.render().unwrap();
-        scheduler.consume()?;
+        let data = self.scheduler().lock().unwrap().render().unwrap();
+        self.consume()?;
+        self.world().lock().unwrap().advance_tick();
+        self.orders().lock().unwrap().advance_tick();
+        self.sample();
         Ok(data)
     }
-    fn tick_start(&mut self, file: &mut File) -> std::io::Result<()> {
-        file.write_all(b"{ \"data\": [")
-    }
-    fn tick_and_save(&mut self, file: &mut File) -> std::io::Result<()> {
-        let scheduler_data = self.tick().unwrap();
+    fn tick_and_save(&mut self, sink: &mut TrajectorySink) -> io::Result<()> {
+        let tick = self.world().lock().unwrap().tick();
+        let scheduler = self.tick().map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
         let world = self.world();
-        let world = world.lock().unwrap();
-        file.write_all(b"{ \"objects\": ")?;
-        file.write_all(world.render().unwrap().as_bytes())?;
-        file.write_all(b", \"scheduler\": ")?;
-        file.write_all(scheduler_data.as_bytes())?;
-        file.write_all(b"}, ")
+        let frame = Frame::capture(tick, &mut world.lock().unwrap(), scheduler);
+        sink.write_frame(&frame)
     }
-    fn tick_end(&mut self, file: &mut File) -> std::io::Result<()> {
-        file.write_all(b"{}]}")
+    fn finish(&mut self, sink: &mut TrajectorySink) -> io::Result<()> {
+        sink.write_meta("orders", &self.orders().lock().unwrap().render())?;
+        sink.write_meta("catalog", &self.world().lock().unwrap().catalog().render())?;
+        sink.finish()
     }
 }
 
@@ -42,20 +49,98 @@ pub struct OneRunner {
     bots: Vec<Arc<Mutex<Bot>>>,
     shelves: Vec<Arc<Mutex<Shelf>>>,
     sites: Vec<Arc<Mutex<Site>>>,
-    scheduler: Arc<Mutex<ParallelCommandQueue>>
+    scheduler: Arc<Mutex<ParallelCommandQueue>>,
+    orders: Arc<Mutex<OrderBook>>,
+    dispatcher: Box<Dispatcher>,
+    failures: Vec<Failure>
+}
+
+pub fn assign_orders(orders: &Arc<Mutex<OrderBook>>, sites: &Vec<Arc<Mutex<Site>>>, scheduler: &mut CommandQueue) -> Result<(), Error> {
+    for site in sites {
+        let site_id = site.lock().unwrap().id().to_owned();
+        if *site.lock().unwrap().get_lock() {
+            continue;
+        }
+        let order = {
+            let orders = orders.lock().unwrap();
+            if orders.active(&site_id).is_some() {
+                continue;
+            }
+            let site = site.lock().unwrap();
+            let pending = orders.pending();
+            match pending.iter().find(|order| order.lines.iter().all(|line| site.storage().have(line.0) >= line.1)) {
+                Some(order) => (order.id.clone(), order.lines.clone()),
+                None => continue
+            }
+        };
+        let mut queue = SequentialCommandQueue::new();
+        for line in order.1 {
+            queue.schedule(SitePickCommand::for_order(site.clone(), line, orders.clone(), &order.0))?;
+        }
+        orders.lock().unwrap().assign(&order.0, &site_id)?;
+        if let Err(err) = scheduler.schedule(Box::new(queue)) {
+            orders.lock().unwrap().release(&order.0)?;
+            return Err(err);
+        }
+    }
+    Ok(())
 }
 
 impl OneRunner {
     pub fn new() -> Self {
-        let data = one_bot();
+        Self::with_data(one_bot())
+    }
+    pub fn with_data(data: WorldData) -> Self {
         Self {
             world: Arc::new(Mutex::new(data.0)),
             bots: data.1,
             shelves: data.2,
             sites: data.3,
-            scheduler: Arc::new(Mutex::new(ParallelCommandQueue::new()))
+            scheduler: Arc::new(Mutex::new(ParallelCommandQueue::with_policy(FailurePolicy::Skip))),
+            orders: Arc::new(Mutex::new(OrderBook::new())),
+            dispatcher: Box::new(GreedyDispatcher::new()),
+            failures: Vec::new()
+        }
+    }
+    pub fn with_snapshot(snapshot: Snapshot) -> Self {
+        Self {
+            world: snapshot.world,
+            bots: snapshot.bots,
+            shelves: snapshot.shelves,
+            sites: snapshot.sites,
+            scheduler: snapshot.scheduler,
+            orders: snapshot.orders,
+            dispatcher: Box::new(GreedyDispatcher::new()),
+            failures: Vec::new()
         }
     }
+    pub fn with_dispatcher(data: WorldData, dispatcher: Box<Dispatcher>) -> Self {
+        Self {
+            dispatcher: dispatcher,
+            ..Self::with_data(data)
+        }
+    }
+    pub fn with_orders(data: WorldData, orders: OrderBook) -> Self {
+        Self {
+            orders: Arc::new(Mutex::new(orders)),
+            ..Self::with_data(data)
+        }
+    }
+    pub fn failures(&self) -> &Vec<Failure> {
+        &self.failures
+    }
+    fn release_orders(&mut self) -> Result<(), Error> {
+        let running = self.scheduler.lock().unwrap().orders();
+        let mut orders = self.orders.lock().unwrap();
+        let orphaned = orders.assigned().iter()
+            .filter(|order| !running.contains(&order.id))
+            .map(|order| order.id.clone())
+            .collect::<Vec<String>>();
+        for id in orphaned {
+            orders.release(&id)?;
+        }
+        Ok(())
+    }
 }
 
 impl Runner for OneRunner {
@@ -65,14 +150,247 @@ impl Runner for OneRunner {
     fn world(&self) -> Arc<Mutex<World>> {
         self.world.clone()
     }
-    fn task(&mut self) -> Result<(), &'static str> {
+    fn orders(&self) -> Arc<Mutex<OrderBook>> {
+        self.orders.clone()
+    }
+    fn task(&mut self) -> Result<(), Error> {
         let mut scheduler = self.scheduler.lock().unwrap();
-        let bot = &self.bots[0];
-        let target_location = {
-            let bot = bot.lock().unwrap();
-            bot.location().up()
-        };
-        scheduler.schedule(BotMoveCommand::new(bot.clone(), target_location, self.world.clone()))?;
+        assign_orders(&self.orders, &self.sites, &mut *scheduler)?;
+        let jobs = self.dispatcher.dispatch(&DispatchContext {
+            world: &self.world,
+            bots: &self.bots,
+            shelves: &self.shelves,
+            sites: &self.sites,
+            orders: &self.orders
+        })?;
+        for job in jobs {
+            let command = job.command.render()?;
+            match scheduler.schedule(job.command) {
+                Ok(()) => self.orders.lock().unwrap().assign(&job.order, &job.site)?,
+                Err(err) => {
+                    let mut world = self.world.lock().unwrap();
+                    world.emit(Event::CommandFailed { command: command.clone(), error: err.clone() });
+                    self.failures.push(Failure { tick: world.tick(), command: command, error: err });
+                }
+            }
+        }
         Ok(())
     }
+    fn idle(&self) -> bool {
+        self.scheduler.lock().unwrap().commands().len() == 0
+    }
+    fn snapshot(&self) -> Result<Snapshot, Error> {
+        Snapshot::capture(&self.world, &self.bots, &self.shelves, &self.sites, &self.scheduler, &self.orders)
+    }
+    fn restore(&mut self, snapshot: &Snapshot) -> Result<(), Error> {
+        let snapshot = snapshot.fork()?;
+        self.world = snapshot.world;
+        self.bots = snapshot.bots;
+        self.shelves = snapshot.shelves;
+        self.sites = snapshot.sites;
+        self.scheduler = snapshot.scheduler;
+        self.orders = snapshot.orders;
+        Ok(())
+    }
+    fn consume(&mut self) -> Result<(), Error> {
+        let context = SnapshotContext::new(self.world.clone(), self.orders.clone(), &self.bots, &self.shelves, &self.sites);
+        let failures = self.scheduler.lock().unwrap().consume_atomic(&context)?;
+        if failures.len() > 0 {
+            self.release_orders()?;
+        }
+        self.failures.extend(failures);
+        Ok(())
+    }
+    fn sample(&mut self) {
+        self.world.lock().unwrap().get_metrics().sample(&self.bots, &self.sites);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::{ OrderStatus, EventLog };
+    use warehouse::dispatcher::Job;
+    use warehouse::trajectory::{ JsonSink, ObjectState };
+
+    #[test]
+    fn test_assign_orders() {
+        let sites = vec![Site::new(), Site::new()];
+        sites[1].lock().unwrap().get_storage().add(1, 5).unwrap();
+        let orders = Arc::new(Mutex::new(OrderBook::new()));
+        let (small, large, other) = {
+            let mut orders = orders.lock().unwrap();
+            (orders.add(vec![(1, 2)], 0, 10).unwrap(), orders.add(vec![(1, 9)], 1, 10).unwrap(), orders.add(vec![(2, 1)], 0, 10).unwrap())
+        };
+        let mut scheduler = ParallelCommandQueue::new();
+        scheduler.initialize().unwrap();
+        assign_orders(&orders, &sites, &mut scheduler).unwrap();
+        assert_eq!(scheduler.commands().len(), 1);
+        {
+            let orders = orders.lock().unwrap();
+            assert_eq!(orders.active(sites[1].lock().unwrap().id()).unwrap().id, small);
+            assert_eq!(orders.get(&large).unwrap().status, OrderStatus::Pending);
+            assert_eq!(orders.get(&other).unwrap().status, OrderStatus::Pending);
+        }
+        assert!(!scheduler.consume().unwrap());
+        assert_eq!(orders.lock().unwrap().get(&small).unwrap().status, OrderStatus::Complete);
+        assert_eq!(sites[1].lock().unwrap().pick_storage.have(1), 2);
+    }
+
+    #[test]
+    fn test_dispatch_orders() {
+        let data = one_bot();
+        data.2[0].lock().unwrap().get_storage().add(1, 3).unwrap();
+        let mut runner = OneRunner::with_data(data);
+        let order = runner.orders().lock().unwrap().add(vec![(1, 2)], 0, 100).unwrap();
+        runner.tick().unwrap();
+        assert!(!runner.idle());
+        for _ in 0..100 {
+            runner.tick().unwrap();
+            if runner.idle() {
+                break;
+            }
+        }
+        assert_eq!(runner.orders().lock().unwrap().get(&order).unwrap().status, OrderStatus::Complete);
+        let world = runner.world();
+        let world = world.lock().unwrap();
+        let stats = world.metrics().total();
+        assert!(stats.distance > 0);
+        assert_eq!(stats.transfers, 2);
+        assert_eq!(stats.busy + stats.idle, world.metrics().ticks());
+        assert_eq!(world.metrics().picked(), 2);
+    }
+
+    struct EmptySiteDispatcher;
+
+    impl Dispatcher for EmptySiteDispatcher {
+        fn dispatch(&mut self, context: &DispatchContext) -> Result<Vec<Job>, Error> {
+            let orders = context.orders.lock().unwrap();
+            Ok(orders.pending().iter()
+                .map(|order| Job {
+                    order: order.id.clone(),
+                    site: context.sites[0].lock().unwrap().id().to_owned(),
+                    command: SitePickCommand::new(context.sites[0].clone(), order.lines[0]) as Box<Command>
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_dispatch_failed() {
+        let mut runner = OneRunner::with_dispatcher(one_bot(), Box::new(EmptySiteDispatcher));
+        let order = runner.orders().lock().unwrap().add(vec![(1, 2)], 0, 100).unwrap();
+        runner.tick().unwrap();
+        assert_eq!(runner.failures().len(), 1);
+        assert_eq!(runner.failures()[0].error, Error::NotEnoughItem { item: 1, have: 0, want: 2 });
+        assert_eq!(runner.orders().lock().unwrap().get(&order).unwrap().status, OrderStatus::Pending);
+        assert!(runner.idle());
+    }
+
+    fn dispatched() -> (OneRunner, String) {
+        let data = one_bot();
+        data.2[0].lock().unwrap().get_storage().add(1, 3).unwrap();
+        let mut runner = OneRunner::with_data(data);
+        let order = runner.orders().lock().unwrap().add(vec![(1, 2)], 0, 100).unwrap();
+        for _ in 0..3 {
+            runner.tick().unwrap();
+        }
+        (runner, order)
+    }
+
+    #[test]
+    fn test_job_failed() {
+        let (mut runner, order) = dispatched();
+        let shelf = runner.shelves[0].clone();
+        shelf.lock().unwrap().lock().unwrap();
+        while !runner.idle() {
+            runner.tick().unwrap();
+        }
+        assert_eq!(runner.failures().len(), 1);
+        assert_eq!(runner.failures()[0].error, Error::AlreadyLocked { id: shelf.lock().unwrap().id().to_owned() });
+        assert_eq!(runner.orders().lock().unwrap().get(&order).unwrap().status, OrderStatus::Pending);
+        shelf.lock().unwrap().unlock().unwrap();
+        runner.tick().unwrap();
+        assert!(!runner.idle());
+        while !runner.idle() {
+            runner.tick().unwrap();
+        }
+        assert_eq!(runner.orders().lock().unwrap().get(&order).unwrap().status, OrderStatus::Complete);
+    }
+
+    fn finish_jobs(runner: &mut OneRunner) -> Vec<ObjectState> {
+        while !runner.idle() {
+            runner.tick().unwrap();
+        }
+        let world = runner.world();
+        let mut world = world.lock().unwrap();
+        Frame::capture(0, &mut world, "{}".to_owned()).objects
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let (mut runner, order) = dispatched();
+        let snapshot = runner.snapshot().unwrap();
+        assert_eq!(snapshot.tick(), 3);
+        let expected = finish_jobs(&mut runner);
+        assert_eq!(runner.orders().lock().unwrap().get(&order).unwrap().status, OrderStatus::Complete);
+        for _ in 0..2 {
+            runner.restore(&snapshot).unwrap();
+            assert_eq!(runner.world().lock().unwrap().tick(), 3);
+            assert_eq!(runner.orders().lock().unwrap().get(&order).unwrap().status, OrderStatus::Assigned);
+            assert!(!runner.idle());
+            assert_eq!(finish_jobs(&mut runner), expected);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_checkpoint() {
+        let (mut runner, order) = dispatched();
+        let value = runner.snapshot().unwrap().to_value().unwrap();
+        let expected = finish_jobs(&mut runner);
+        let distance = runner.world().lock().unwrap().metrics().total().distance;
+        let mut resumed = OneRunner::with_snapshot(Snapshot::from_value(&value).unwrap());
+        assert_eq!(finish_jobs(&mut resumed), expected);
+        assert_eq!(resumed.orders().lock().unwrap().get(&order).unwrap().status, OrderStatus::Complete);
+        assert_eq!(resumed.world().lock().unwrap().metrics().total().distance, distance);
+    }
+
+    #[test]
+    fn test_events() {
+        let data = one_bot();
+        data.2[0].lock().unwrap().get_storage().add(1, 3).unwrap();
+        let mut runner = OneRunner::with_data(data);
+        let log = EventLog::new();
+        runner.world().lock().unwrap().subscribe(log.clone());
+        runner.orders().lock().unwrap().add(vec![(1, 2)], 0, 100).unwrap();
+        runner.tick().unwrap();
+        while !runner.idle() {
+            runner.tick().unwrap();
+        }
+        let log = log.lock().unwrap();
+        let count = |name: &str| log.events.iter().filter(|&&(_, ref event)| event.render().contains(name)).count();
+        assert_eq!(count("command_initialized"), count("command_completed"));
+        assert_eq!(count("object_locked"), count("object_unlocked"));
+        assert_eq!(count("command_failed"), 0);
+        assert!(count("object_moved") > 0);
+        assert!(count("storage_changed") > 0);
+        assert!(log.events.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    #[test]
+    fn test_finish_orders() {
+        let mut runner = OneRunner::new();
+        runner.orders().lock().unwrap().add(vec![(1, 1)], 0, 10).unwrap();
+        let mut data: Vec<u8> = Vec::new();
+        {
+            let mut sink = JsonSink::new(&mut data);
+            runner.tick_and_save(&mut sink).unwrap();
+            runner.finish(&mut sink).unwrap();
+        }
+        let data = String::from_utf8(data).unwrap();
+        assert!(data.starts_with("{ \"data\": [{ \"tick\": 0, \"objects\": ["));
+        assert!(data.ends_with("}], \"orders\": [{ \"id\": \"ord-0001\", \"lines\": [{ \"item\": 1, \"count\": 1 }], \"priority\": 0, \"due\": 10, \"status\": \"pending\", \"site\": null, \"remaining\": [{ \"item\": 1, \"count\": 1 }], \"created\": 0, \"completed\": null }], \"catalog\": [] }\n"));
+        assert_eq!(runner.orders().lock().unwrap().tick(), 1);
+    }
 }
@@ -0,0 +1,207 @@
+use warehouse::{ World, OrderBook, IdGenerator };
+#[cfg(feature = "serde")]
+use warehouse::ItemCatalog;
+use warehouse::object::{ Object, Bot, Shelf, Site };
+use warehouse::trajectory::{ Frame, ObjectState, Record, DeltaDecoder, frame_at, read_records };
+use worlddata::util::{ map_as_object, WorldData };
+#[cfg(feature = "serde")]
+use serde_json;
+use std::fs::File;
+use std::io::{ self, Read };
+use std::path::Path;
+use std::sync::{ Arc, Mutex };
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn fill(obj: Arc<Mutex<Object>>, state: &ObjectState) {
+    let mut obj = obj.lock().unwrap();
+    *obj.get_location() = state.location;
+    *obj.get_lock() = state.locked;
+    obj.get_storage().items = state.storage.clone();
+}
+
+fn last_frame(frames: Vec<Frame>) -> io::Result<Option<Frame>> {
+    let mut decoder = DeltaDecoder::new();
+    let mut last = None;
+    for frame in &frames {
+        decoder.apply(frame)?;
+        last = Some(frame);
+    }
+    Ok(last.map(|frame| Frame { objects: decoder.objects().clone(), delta: false, ..frame.clone() }))
+}
+
+#[cfg(feature = "serde")]
+fn set_catalog(world: &mut World, records: &[Record]) -> io::Result<()> {
+    for record in records {
+        if let Record::Meta(ref key, ref value) = *record {
+            if key == "catalog" {
+                world.set_catalog(Arc::new(serde_json::from_str::<ItemCatalog>(value)?));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn set_catalog(_: &mut World, _: &[Record]) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+pub fn restore_orders(records: &[Record], tick: u64) -> io::Result<OrderBook> {
+    let mut orders = OrderBook::new();
+    for record in records {
+        if let Record::Meta(ref key, ref value) = *record {
+            if key == "orders" {
+                orders = OrderBook::from_render(value, tick).map_err(|err| invalid(err.to_string()))?;
+            }
+        }
+    }
+    // no job survives a replay, so assigned orders go back to the dispatcher
+    let assigned = orders.assigned().iter().map(|order| order.id.clone()).collect::<Vec<String>>();
+    for id in assigned {
+        orders.release(&id).map_err(|err| invalid(err.to_string()))?;
+    }
+    Ok(orders)
+}
+
+#[cfg(not(feature = "serde"))]
+pub fn restore_orders(_: &[Record], _: u64) -> io::Result<OrderBook> {
+    Ok(OrderBook::new())
+}
+
+pub fn restore(records: &[Record], tick: Option<u64>, ids: IdGenerator) -> io::Result<WorldData> {
+    let frames = records.iter()
+        .filter_map(|record| match *record {
+            Record::Frame(ref frame) => Some(frame.clone()),
+            _ => None
+        })
+        .collect::<Vec<Frame>>();
+    let frame = match tick {
+        Some(tick) => frame_at(frames, tick)?.ok_or(invalid(format!("no frame recorded at tick {}", tick)))?,
+        None => last_frame(frames)?.ok_or(invalid("no frames recorded".to_owned()))?
+    };
+    let mut world = World::with_ids(ids);
+    let mut vec_bot: Vec<Arc<Mutex<Bot>>> = Vec::new();
+    let mut vec_shelf: Vec<Arc<Mutex<Shelf>>> = Vec::new();
+    let mut vec_site: Vec<Arc<Mutex<Site>>> = Vec::new();
+    let mut items: Vec<Arc<Mutex<Object>>> = Vec::new();
+    for state in &frame.objects {
+        let item: Arc<Mutex<Object>> = if state.id.starts_with("bot-") {
+            let bot = Bot::with_id(state.id.clone());
+            vec_bot.push(bot.clone());
+            bot
+        } else if state.id.starts_with("slf-") {
+            let shelf = Shelf::with_id(state.id.clone());
+            vec_shelf.push(shelf.clone());
+            shelf
+        } else if state.id.starts_with("ste-") {
+            let site = Site::with_id(state.id.clone());
+            site.lock().unwrap().pick_storage.items = state.pick_storage.clone().unwrap_or(vec![]);
+            vec_site.push(site.clone());
+            site
+        } else {
+            return Err(invalid(format!("unknown object {}", state.id)));
+        };
+        fill(item.clone(), state);
+        items.push(item);
+    }
+    world.add_items(items);
+    set_catalog(&mut world, records)?;
+    world.set_tick(frame.tick + 1);
+    Ok((world, vec_bot, vec_shelf, vec_site))
+}
+
+pub fn load_replay<P: AsRef<Path>>(path: P, tick: Option<u64>, ids: IdGenerator) -> io::Result<(WorldData, OrderBook)> {
+    let mut data = Vec::new();
+    File::open(path)?.read_to_end(&mut data)?;
+    let records = read_records(&data)?;
+    let data = restore(&records, tick, ids)?;
+    let orders = restore_orders(&records, data.0.tick())?;
+    Ok((data, orders))
+}
+
+pub fn unlock_all(data: &WorldData) {
+    for item in map_as_object(&data.1).into_iter().chain(map_as_object(&data.2)).chain(map_as_object(&data.3)) {
+        *item.lock().unwrap().get_lock() = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warehouse::trajectory::{ TrajectorySink, DeltaSink, BinarySink };
+    use worlddata::{ OneRunner, Runner, one_bot };
+
+    fn record(ticks: u64) -> (Vec<Record>, Vec<Frame>) {
+        let mut runner = OneRunner::new();
+        runner.orders().lock().unwrap().add(vec![(1, 1)], 0, 10).unwrap();
+        let mut data: Vec<u8> = Vec::new();
+        let mut frames = Vec::new();
+        {
+            let mut sink = DeltaSink::new(Box::new(BinarySink::new(&mut data)), 4);
+            for _ in 0..ticks {
+                runner.tick_and_save(&mut sink).unwrap();
+                let world = runner.world();
+                let mut world = world.lock().unwrap();
+                frames.push(Frame::capture(world.tick() - 1, &mut world, "{}".to_owned()));
+            }
+            runner.finish(&mut sink).unwrap();
+        }
+        (read_records(&data).unwrap(), frames)
+    }
+
+    #[test]
+    fn test_restore() {
+        let (records, frames) = record(10);
+        for tick in vec![0, 5, 9] {
+            let (mut world, bots, shelves, sites) = restore(&records, Some(tick), IdGenerator::sequential()).unwrap();
+            assert_eq!(world.tick(), tick + 1);
+            assert_eq!(Frame::capture(tick, &mut world, "{}".to_owned()).objects, frames[tick as usize].objects);
+            let (_, expected_bots, expected_shelves, expected_sites) = one_bot();
+            assert_eq!((bots.len(), shelves.len(), sites.len()), (expected_bots.len(), expected_shelves.len(), expected_sites.len()));
+        }
+        let (world, _, _, _) = restore(&records, None, IdGenerator::sequential()).unwrap();
+        assert_eq!(world.tick(), 10);
+    }
+
+    #[test]
+    fn test_resume() {
+        let (records, _) = record(3);
+        let data = restore(&records, None, IdGenerator::sequential()).unwrap();
+        unlock_all(&data);
+        assert!(data.1.iter().all(|bot| !*bot.lock().unwrap().get_lock()));
+        let mut runner = OneRunner::with_data(data);
+        runner.tick().unwrap();
+        assert_eq!(runner.world().lock().unwrap().tick(), 4);
+        assert_eq!(runner.world().lock().unwrap().next_id("bot"), "bot-0002");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_restore_orders() {
+        let (records, _) = record(3);
+        let orders = restore_orders(&records, 3).unwrap();
+        assert_eq!(orders.orders().len(), 1);
+        assert_eq!(orders.orders()[0].lines, vec![(1, 1)]);
+        assert_eq!(orders.orders()[0].status, ::warehouse::OrderStatus::Pending);
+        assert_eq!(orders.tick(), 3);
+    }
+
+    #[test]
+    #[should_panic(expected="no frame recorded at tick 20")]
+    fn test_missing_tick() {
+        let (records, _) = record(3);
+        restore(&records, Some(20), IdGenerator::sequential()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected="unknown object b\u{e9}\u{e9}-0001")]
+    fn test_unknown_object() {
+        let state = ObjectState { id: "b\u{e9}\u{e9}-0001".to_owned(), location: Default::default(), locked: false, storage: vec![], pick_storage: None };
+        let frame = Frame { tick: 0, objects: vec![state], scheduler: "{}".to_owned(), delta: false };
+        restore(&[Record::Frame(frame)], None, IdGenerator::sequential()).unwrap();
+    }
+}
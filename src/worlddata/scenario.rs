@@ -0,0 +1,25 @@
+use warehouse::IdGenerator;
+use worlddata::one_bot_with_ids;
+use worlddata::util::WorldData;
+
+pub const SCENARIOS: &'static [&'static str] = &["one_bot"];
+
+pub fn scenario(name: &str, ids: IdGenerator) -> Option<WorldData> {
+    match name {
+        "one_bot" => Some(one_bot_with_ids(ids)),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scenario() {
+        for name in SCENARIOS {
+            assert!(scenario(name, IdGenerator::sequential()).is_some());
+        }
+        assert!(scenario("two_bot", IdGenerator::sequential()).is_none());
+    }
+}
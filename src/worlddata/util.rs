@@ -1,6 +1,12 @@
-use warehouse::object::{ Object, Location };
+use warehouse::World;
+use warehouse::object::{ Object, Location, Bot, Shelf, Site };
 use std::sync::{ Arc, Mutex };
 
+pub type WorldData = (World,
+    Vec<Arc<Mutex<Bot>>>,
+    Vec<Arc<Mutex<Shelf>>>,
+    Vec<Arc<Mutex<Site>>>);
+
 pub fn move_location(obj: Arc<Mutex<Object>>, location: Location) {
     let mut obj = obj.lock().unwrap();
     let loc = obj.get_location();